@@ -0,0 +1,246 @@
+use crate::id::{IdKind, L1Id, Source};
+use crate::kugou::{self, Kugou};
+use crate::kuwo::{self, Kuwo};
+use crate::media::{
+  Bitrate, L1AlbumDetail, L1AlbumInfo, L1ArtistDetail, L1Lyrics, L1PlaylistDetail, L1SearchResult,
+  L1TrackStream, L1TrackUrl, Pagination, Provider, ProviderError,
+};
+use crate::migu::{self, Migu};
+use crate::netease::{self, CookieJar, Netease};
+use crate::qq::{self, QQ};
+use reqwest::Client;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProviderEndpoints {
+  pub netease: netease::Endpoints,
+  pub qq: qq::Endpoints,
+  pub kugou: kugou::Endpoints,
+  pub kuwo: kuwo::Endpoints,
+  pub migu: migu::Endpoints,
+}
+
+impl ProviderEndpoints {
+  /// Points every upstream host at `base`, e.g. a local mock server.
+  pub fn all(base: &str) -> Self {
+    let base = base.to_string();
+    ProviderEndpoints {
+      netease: netease::Endpoints { api: base.clone() },
+      qq: qq::Endpoints {
+        c: base.clone(),
+        i: base.clone(),
+        u: base.clone(),
+      },
+      kugou: kugou::Endpoints {
+        mobile: base.clone(),
+        mobile_cdn: base.clone(),
+        search: base.clone(),
+        web: base.clone(),
+      },
+      kuwo: kuwo::Endpoints {
+        api: base.clone(),
+        anti: base.clone(),
+      },
+      migu: migu::Endpoints { search: base },
+    }
+  }
+}
+
+pub struct Listen1 {
+  netease: Client,
+  qq: Client,
+  kugou: Client,
+  kuwo: Client,
+  migu: Client,
+  endpoints: ProviderEndpoints,
+  netease_cookies: Arc<CookieJar>,
+}
+
+impl Default for Listen1 {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Listen1 {
+  pub fn new() -> Self {
+    Self::with_endpoints(ProviderEndpoints::default())
+  }
+
+  pub fn with_endpoints(endpoints: ProviderEndpoints) -> Self {
+    let netease_cookies = Arc::new(Netease::create_cookie_jar());
+    Listen1 {
+      netease: Netease::create_client_with_jar(netease_cookies.clone()),
+      qq: Client::new(),
+      kugou: Kugou::create_client(),
+      kuwo: Kuwo::create_client(),
+      migu: Migu::create_client(),
+      endpoints,
+      netease_cookies,
+    }
+  }
+
+  pub fn endpoints(&self) -> &ProviderEndpoints {
+    &self.endpoints
+  }
+
+  /// The Netease session; serialise it to keep a login, `restore` it to reuse one.
+  pub fn netease_cookies(&self) -> &CookieJar {
+    &self.netease_cookies
+  }
+
+  /// Netease with its account calls, sharing the session above.
+  pub fn netease(&self) -> Netease<'_> {
+    Netease::with_endpoints(&self.netease, self.endpoints.netease.clone())
+      .with_cookies(&self.netease_cookies)
+  }
+
+  pub fn provider(&self, source: Source) -> Box<dyn Provider + '_> {
+    let endpoints = &self.endpoints;
+    match source {
+      Source::Netease => Box::new(self.netease()),
+      Source::QQ => Box::new(QQ::with_endpoints(&self.qq, endpoints.qq.clone())),
+      Source::Kugou => Box::new(Kugou::with_endpoints(&self.kugou, endpoints.kugou.clone())),
+      Source::Kuwo => Box::new(Kuwo::with_endpoints(&self.kuwo, endpoints.kuwo.clone())),
+      Source::Migu => Box::new(Migu::with_endpoints(&self.migu, endpoints.migu.clone())),
+    }
+  }
+
+  fn route(
+    &self,
+    id: &str,
+    kind: IdKind,
+  ) -> Result<(Box<dyn Provider + '_>, String), ProviderError> {
+    let id = id.parse::<L1Id>()?.expect_kind(kind)?;
+
+    Ok((self.provider(id.source), id.id))
+  }
+
+  pub async fn get_playlist_detail(
+    &self,
+    playlist_id: &str,
+  ) -> Result<L1PlaylistDetail, ProviderError> {
+    let (provider, raw_id) = self.route(playlist_id, IdKind::Playlist)?;
+    provider.get_playlist_detail(&raw_id).await
+  }
+
+  pub async fn get_track_url(&self, track_id: &str) -> Result<L1TrackUrl, ProviderError> {
+    let (provider, raw_id) = self.route(track_id, IdKind::Track)?;
+    provider.get_track_url(&raw_id).await
+  }
+
+  pub async fn get_track_stream(
+    &self,
+    track_id: &str,
+    bitrate: Bitrate,
+  ) -> Result<L1TrackStream, ProviderError> {
+    let (provider, raw_id) = self.route(track_id, IdKind::Track)?;
+    provider.get_track_stream(&raw_id, bitrate).await
+  }
+
+  pub async fn get_lyrics(&self, track_id: &str) -> Result<L1Lyrics, ProviderError> {
+    let (provider, raw_id) = self.route(track_id, IdKind::Track)?;
+    provider.get_lyrics(&raw_id).await
+  }
+
+  pub async fn get_album(&self, album_id: &str) -> Result<L1AlbumDetail, ProviderError> {
+    let (provider, raw_id) = self.route(album_id, IdKind::Album)?;
+    provider.get_album(&raw_id).await
+  }
+
+  pub async fn get_artist(&self, artist_id: &str) -> Result<L1ArtistDetail, ProviderError> {
+    let (provider, raw_id) = self.route(artist_id, IdKind::Artist)?;
+    provider.get_artist(&raw_id).await
+  }
+
+  pub async fn get_artist_albums(
+    &self,
+    artist_id: &str,
+    pagination: &Pagination,
+  ) -> Result<L1SearchResult<L1AlbumInfo>, ProviderError> {
+    let (provider, raw_id) = self.route(artist_id, IdKind::Artist)?;
+    provider.get_artist_albums(&raw_id, pagination).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::dispatcher::{Listen1, ProviderEndpoints};
+  use crate::id::IdKind;
+  use crate::media::ProviderError;
+
+  #[test]
+  fn route_prefixed_ids() {
+    let listen1 = Listen1::new();
+
+    let (provider, raw_id) = listen1
+      .route("qqplaylist_7256912512", IdKind::Playlist)
+      .unwrap();
+    assert_eq!(provider.name(), "qq");
+    assert_eq!(raw_id, "7256912512");
+
+    let (provider, raw_id) = listen1.route("kgtrack_ABC_123", IdKind::Track).unwrap();
+    assert_eq!(provider.name(), "kugou");
+    assert_eq!(raw_id, "ABC_123");
+
+    assert!(matches!(
+      listen1.route("neplaylist_1", IdKind::Track),
+      Err(ProviderError::InvalidParameter { .. })
+    ));
+    assert!(matches!(
+      listen1.route("xxtrack_1", IdKind::Track),
+      Err(ProviderError::InvalidParameter { .. })
+    ));
+    assert!(listen1.route("qqtrack_", IdKind::Track).is_err());
+  }
+
+  #[test]
+  fn override_endpoints() {
+    assert_eq!(
+      Listen1::new().endpoints().netease.api,
+      "https://music.163.com"
+    );
+
+    let listen1 = Listen1::with_endpoints(ProviderEndpoints::all("http://127.0.0.1:3030"));
+    let endpoints = listen1.endpoints();
+    assert_eq!(endpoints.qq.i, "http://127.0.0.1:3030");
+    assert_eq!(endpoints.kugou.mobile_cdn, "http://127.0.0.1:3030");
+    assert_eq!(endpoints.kuwo.anti, "http://127.0.0.1:3030");
+    assert_eq!(endpoints.migu.search, "http://127.0.0.1:3030");
+  }
+
+  #[tokio::test]
+  async fn get_netease_lyrics() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("POST", "/weapi/song/lyric?csrf_token=")
+      .with_body(include_str!("../fixtures/netease/song_lyric.json"))
+      .create_async()
+      .await;
+
+    let listen1 = Listen1::with_endpoints(ProviderEndpoints::all(&server.url()));
+    let lyrics = listen1.get_lyrics("netrack_186016").await.unwrap();
+
+    assert!(lyrics.lyric.contains("[00:29.350]故事的小黄花"));
+    assert!(lyrics
+      .tlyric
+      .unwrap()
+      .contains("The little yellow flower of the story"));
+  }
+
+  #[tokio::test]
+  async fn get_netease_lyrics_for_instrumental() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("POST", "/weapi/song/lyric?csrf_token=")
+      .with_body(include_str!("../fixtures/netease/song_nolyric.json"))
+      .create_async()
+      .await;
+
+    let listen1 = Listen1::with_endpoints(ProviderEndpoints::all(&server.url()));
+    let lyrics = listen1.get_lyrics("netrack_1357375695").await.unwrap();
+
+    assert_eq!(lyrics.lyric, "");
+    assert!(lyrics.tlyric.is_none());
+  }
+}
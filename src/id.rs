@@ -0,0 +1,260 @@
+use crate::media::ProviderError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+  Netease,
+  QQ,
+  Kugou,
+  Kuwo,
+  Migu,
+}
+
+impl Source {
+  pub const ALL: [Source; 5] = [
+    Source::Netease,
+    Source::QQ,
+    Source::Kugou,
+    Source::Kuwo,
+    Source::Migu,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Source::Netease => "netease",
+      Source::QQ => "qq",
+      Source::Kugou => "kugou",
+      Source::Kuwo => "kuwo",
+      Source::Migu => "migu",
+    }
+  }
+
+  pub fn prefix(&self) -> &'static str {
+    match self {
+      Source::Netease => "ne",
+      Source::QQ => "qq",
+      Source::Kugou => "kg",
+      Source::Kuwo => "kw",
+      Source::Migu => "mg",
+    }
+  }
+
+  pub fn from_prefix(prefix: &str) -> Option<Source> {
+    Source::ALL.into_iter().find(|x| x.prefix() == prefix)
+  }
+
+  pub fn track(self, id: impl Into<String>) -> L1Id {
+    L1Id::new(self, IdKind::Track, id)
+  }
+
+  pub fn playlist(self, id: impl Into<String>) -> L1Id {
+    L1Id::new(self, IdKind::Playlist, id)
+  }
+
+  pub fn album(self, id: impl Into<String>) -> L1Id {
+    L1Id::new(self, IdKind::Album, id)
+  }
+
+  pub fn artist(self, id: impl Into<String>) -> L1Id {
+    L1Id::new(self, IdKind::Artist, id)
+  }
+
+  pub fn track_ref(self, id: impl Into<String>) -> Option<L1Id> {
+    L1Id::optional(self, IdKind::Track, id)
+  }
+
+  pub fn album_ref(self, id: impl Into<String>) -> Option<L1Id> {
+    L1Id::optional(self, IdKind::Album, id)
+  }
+
+  pub fn artist_ref(self, id: impl Into<String>) -> Option<L1Id> {
+    L1Id::optional(self, IdKind::Artist, id)
+  }
+}
+
+impl FromStr for Source {
+  type Err = ParseIdError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Source::ALL
+      .into_iter()
+      .find(|x| x.name() == s || x.prefix() == s)
+      .ok_or_else(|| ParseIdError(s.to_string()))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdKind {
+  Track,
+  Playlist,
+  Album,
+  Artist,
+}
+
+impl IdKind {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      IdKind::Track => "track",
+      IdKind::Playlist => "playlist",
+      IdKind::Album => "album",
+      IdKind::Artist => "artist",
+    }
+  }
+
+  fn from_str(s: &str) -> Option<IdKind> {
+    [
+      IdKind::Track,
+      IdKind::Playlist,
+      IdKind::Album,
+      IdKind::Artist,
+    ]
+    .into_iter()
+    .find(|x| x.as_str() == s)
+  }
+}
+
+/// A listen1 id such as `neplaylist_123`: source prefix, kind, then the upstream id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct L1Id {
+  pub source: Source,
+  pub kind: IdKind,
+  pub id: String,
+}
+
+impl L1Id {
+  pub fn new(source: Source, kind: IdKind, id: impl Into<String>) -> Self {
+    L1Id {
+      source,
+      kind,
+      id: id.into(),
+    }
+  }
+
+  /// For ids referenced from another item: upstream sends `""` or `"0"` when
+  /// there is none, and such ids would not parse back.
+  pub fn optional(source: Source, kind: IdKind, id: impl Into<String>) -> Option<Self> {
+    let id = id.into();
+    if id.is_empty() || id == "0" {
+      return None;
+    }
+
+    Some(L1Id::new(source, kind, id))
+  }
+
+  pub fn expect_kind(self, kind: IdKind) -> Result<Self, ParseIdError> {
+    if self.kind == kind {
+      Ok(self)
+    } else {
+      Err(ParseIdError(self.to_string()))
+    }
+  }
+}
+
+impl Display for L1Id {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{}{}_{}",
+      self.source.prefix(),
+      self.kind.as_str(),
+      self.id
+    )
+  }
+}
+
+impl FromStr for L1Id {
+  type Err = ParseIdError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || ParseIdError(s.to_string());
+
+    let (prefix, id) = s.split_once('_').ok_or_else(invalid)?;
+    if id.is_empty() || prefix.len() <= 2 || !prefix.is_char_boundary(2) {
+      return Err(invalid());
+    }
+    let (source, kind) = prefix.split_at(2);
+    let source = Source::from_prefix(source).ok_or_else(invalid)?;
+    let kind = IdKind::from_str(kind).ok_or_else(invalid)?;
+
+    Ok(L1Id::new(source, kind, id))
+  }
+}
+
+impl Serialize for L1Id {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for L1Id {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseIdError(String);
+
+impl Display for ParseIdError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "invalid listen1 id {:?}", self.0)
+  }
+}
+
+impl Error for ParseIdError {}
+
+impl From<ParseIdError> for ProviderError {
+  fn from(e: ParseIdError) -> Self {
+    ProviderError::invalid("listen1", "id", &e.0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::id::{IdKind, L1Id, Source};
+
+  #[test]
+  fn parse_and_display() {
+    let id: L1Id = "kgtrack_ABC_123".parse().unwrap();
+    assert_eq!(id.source, Source::Kugou);
+    assert_eq!(id.kind, IdKind::Track);
+    assert_eq!(id.id, "ABC_123");
+    assert_eq!(id.to_string(), "kgtrack_ABC_123");
+
+    assert_eq!(Source::Netease.playlist("42").to_string(), "neplaylist_42");
+
+    assert!("kgtrack_".parse::<L1Id>().is_err());
+    assert!("xxtrack_1".parse::<L1Id>().is_err());
+    assert!("qqsong_1".parse::<L1Id>().is_err());
+    assert!("qq_1".parse::<L1Id>().is_err());
+    assert!("neplaylist_1"
+      .parse::<L1Id>()
+      .unwrap()
+      .expect_kind(IdKind::Track)
+      .is_err());
+  }
+
+  #[test]
+  fn serde_as_string() {
+    let id = Source::QQ.artist("0025NhlN2yWrP4");
+    let json = serde_json::to_string(&id).unwrap();
+    assert_eq!(json, r#""qqartist_0025NhlN2yWrP4""#);
+    assert_eq!(serde_json::from_str::<L1Id>(&json).unwrap(), id);
+    assert!(serde_json::from_str::<L1Id>(r#""qqartist""#).is_err());
+  }
+
+  #[test]
+  fn optional_ids() {
+    assert!(Source::Kugou.album_ref("").is_none());
+    assert!(Source::Kuwo.album_ref("0").is_none());
+    assert_eq!(
+      Source::Kuwo.artist_ref("1024"),
+      Some(Source::Kuwo.artist("1024"))
+    );
+  }
+}
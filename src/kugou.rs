@@ -1,25 +1,142 @@
-use crate::media::{L1PlaylistDetail, L1PlaylistInfo, L1Track, Provider};
+use crate::id::Source;
+use crate::media::{
+  Bitrate, L1PlaylistDetail, L1PlaylistInfo, L1SearchResult, L1Track, L1TrackUrl, PlaylistQuery,
+  Provider, ProviderError, SearchQuery,
+};
+use crate::utils::{
+  endpoint_url, fetch_json, parse_datetime, parse_endpoint_with_params, parse_param, require_param,
+  string_or_number,
+};
 use async_trait::async_trait;
 use futures;
 use reqwest::{header, Client};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use std::collections::HashMap;
 use url::Url;
 
-const HOST: &'static str = "https://www.kuwo.cn";
+const PROVIDER: &str = "kugou";
 
-fn build_playlist_url(params: HashMap<String, String>) -> String {
-  let page: u32 = params.get("page").unwrap().parse().unwrap();
+const MOBILE_HOST: &str = "http://m.kugou.com";
+const MOBILE_CDN_HOST: &str = "http://mobilecdnbj.kugou.com";
+const SEARCH_HOST: &str = "https://songsearch.kugou.com";
+const WEB_HOST: &str = "https://www.kugou.com";
 
-  format!(
-    "http://m.kugou.com/plist/index&json=true&page={page}",
-    page = page,
-  )
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+  pub mobile: String,
+  pub mobile_cdn: String,
+  pub search: String,
+  pub web: String,
+}
+
+impl Default for Endpoints {
+  fn default() -> Self {
+    Self {
+      mobile: MOBILE_HOST.to_string(),
+      mobile_cdn: MOBILE_CDN_HOST.to_string(),
+      search: SEARCH_HOST.to_string(),
+      web: WEB_HOST.to_string(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistParams {
+  pub page: u32,
+}
+
+impl Default for PlaylistParams {
+  fn default() -> Self {
+    Self { page: 1 }
+  }
+}
+
+impl From<&PlaylistQuery> for PlaylistParams {
+  fn from(query: &PlaylistQuery) -> Self {
+    Self {
+      page: query.pagination.page,
+    }
+  }
+}
+
+impl PlaylistParams {
+  pub fn from_query(query: &HashMap<String, String>) -> Result<Self, ProviderError> {
+    let page = require_param(PROVIDER, query, "page")?;
+
+    Ok(Self {
+      page: parse_param(PROVIDER, "page", page)?,
+    })
+  }
+
+  pub fn build_url(&self, endpoints: &Endpoints) -> String {
+    endpoint_url(
+      &endpoints.mobile,
+      &format!("/plist/index&json=true&page={page}", page = self.page),
+    )
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchParams {
+  pub keyword: String,
+  pub page: u32,
+  pub page_size: u32,
+}
+
+impl Default for SearchParams {
+  fn default() -> Self {
+    Self {
+      keyword: "".to_string(),
+      page: 1,
+      page_size: 30,
+    }
+  }
+}
+
+impl From<&SearchQuery> for SearchParams {
+  fn from(query: &SearchQuery) -> Self {
+    Self {
+      keyword: query.keyword.to_string(),
+      page: query.pagination.page,
+      page_size: query.pagination.page_size,
+    }
+  }
+}
+
+impl SearchParams {
+  pub fn from_query(query: &HashMap<String, String>) -> Result<Self, ProviderError> {
+    let mut params = Self {
+      keyword: require_param(PROVIDER, query, "keyword")?.to_string(),
+      ..Default::default()
+    };
+    if let Some(page) = query.get("page") {
+      params.page = parse_param(PROVIDER, "page", page)?;
+    }
+    if let Some(page_size) = query.get("pagesize") {
+      params.page_size = parse_param(PROVIDER, "pagesize", page_size)?;
+    }
+
+    Ok(params)
+  }
+
+  pub fn build_url(&self, endpoints: &Endpoints) -> Result<Url, ProviderError> {
+    parse_endpoint_with_params(
+      PROVIDER,
+      &endpoints.search,
+      "/song_search_v2",
+      &[
+        ("keyword", self.keyword.to_string()),
+        ("page", self.page.to_string()),
+        ("pagesize", self.page_size.to_string()),
+      ],
+    )
+  }
 }
 
 #[derive(Debug)]
 pub struct Kugou<'a> {
   pub client: &'a Client,
+  pub endpoints: Endpoints,
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,6 +144,21 @@ pub struct KugouPlaylistDetail {
   imgurl: String,
   specialname: String,
   specialid: i64,
+  intro: Option<String>,
+  playcount: Option<u64>,
+  songcount: Option<u64>,
+  publishtime: Option<String>,
+  #[serde(alias = "username")]
+  nickname: Option<String>,
+  #[serde(default, deserialize_with = "string_or_number")]
+  suid: Option<String>,
+  #[serde(default)]
+  tags: Vec<KugouTag>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KugouTag {
+  tagname: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,56 +176,43 @@ impl KugouSongInPlaylist {
     )
   }
 
-  async fn get_album(&self, client: &Client) -> AlbumData {
-    let url = format!(
-      "http://mobilecdnbj.kugou.com/api/v3/album/info?albumid={}",
-      self.album_id
-    );
-
-    let result = client
-      .get(&url)
-      .send()
-      .await
-      .unwrap()
-      .json::<AlbumResponse>()
-      .await;
-
-    match result {
-      Ok(album_response) => album_response.data,
-      Err(e) => {
-        println!("get_album failed for url {}", url);
-        println!("Error is {}", e);
-        AlbumData {
-          albumname: String::from(""),
-        }
-      }
+  async fn get_album(
+    &self,
+    client: &Client,
+    endpoints: &Endpoints,
+  ) -> Result<AlbumData, ProviderError> {
+    // Singles carry no album, so there is nothing to look up.
+    if Source::Kugou.album_ref(&self.album_id).is_none() {
+      return Ok(AlbumData {
+        albumname: String::from(""),
+      });
     }
-  }
-
-  async fn get_song_info(&self, client: &Client) -> SongInfo {
-    let url = format!(
-      "http://m.kugou.com/app/i/getSongInfo.php?cmd=playInfo&hash={}",
-      self.hash
-    );
 
-    let result = client
-      .get(&url)
-      .send()
-      .await
-      .unwrap()
-      .json::<SongInfo>()
-      .await;
+    let url = parse_endpoint_with_params(
+      PROVIDER,
+      &endpoints.mobile_cdn,
+      "/api/v3/album/info",
+      &[("albumid", &self.album_id)],
+    )?;
+    let (_, album_response) = fetch_json::<AlbumResponse>(PROVIDER, client.get(url)).await?;
 
-    result.unwrap_or_else(|e| {
-      println!("get_song_info error: {}", e);
+    Ok(album_response.data)
+  }
 
-      SongInfo {
-        song_name: "".to_string(),
-        singer_name: "".to_string(),
-        singer_id: 0,
-        album_img: "".to_string(),
-      }
-    })
+  async fn get_song_info(
+    &self,
+    client: &Client,
+    endpoints: &Endpoints,
+  ) -> Result<SongInfo, ProviderError> {
+    let url = parse_endpoint_with_params(
+      PROVIDER,
+      &endpoints.mobile,
+      "/app/i/getSongInfo.php",
+      &[("cmd", "playInfo"), ("hash", &self.hash)],
+    )?;
+    let (_, info) = fetch_json::<SongInfo>(PROVIDER, client.get(url)).await?;
+
+    Ok(info)
   }
 }
 
@@ -127,11 +246,6 @@ pub struct PlaylistList {
   list: KugouPlaylistListInfo,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct AlbumSong {
-  list: KugouPlaylistListInfo,
-}
-
 #[derive(Debug, Deserialize)]
 struct PlaylistResponse {
   info: PlaylistInfo,
@@ -159,13 +273,6 @@ struct AlbumResponse {
   data: AlbumData,
 }
 
-#[derive(Debug, Deserialize)]
-struct Singer {
-  id: u64,
-  name: String,
-  ip_id: u64,
-}
-
 #[derive(Debug, Deserialize)]
 struct SearchResultItems {
   #[serde(rename(deserialize = "FileHash"))]
@@ -176,12 +283,34 @@ struct SearchResultItems {
   album_name: String,
   #[serde(rename(deserialize = "AlbumID"))]
   album_id: String,
-  #[serde(rename(deserialize = "Singers"))]
-  singers: Vec<Singer>,
   #[serde(rename(deserialize = "SingerId"))]
   singer_id: Vec<u64>,
   #[serde(rename(deserialize = "SingerName"))]
   singer_name: String,
+  #[serde(default, rename(deserialize = "Duration"))]
+  duration: Option<u64>,
+  #[serde(default, rename(deserialize = "FileSize"))]
+  file_size: u64,
+  #[serde(default, rename(deserialize = "HQFileSize"))]
+  hq_file_size: u64,
+  #[serde(default, rename(deserialize = "SQFileSize"))]
+  sq_file_size: u64,
+  #[serde(default, rename(deserialize = "PublishDate"))]
+  publish_date: Option<String>,
+}
+
+impl SearchResultItems {
+  fn bitrates(&self) -> Vec<Bitrate> {
+    [
+      (self.file_size, Bitrate::K128),
+      (self.hq_file_size, Bitrate::K320),
+      (self.sq_file_size, Bitrate::Lossless),
+    ]
+    .into_iter()
+    .filter(|(size, _)| *size > 0)
+    .map(|(_, bitrate)| bitrate)
+    .collect()
+  }
 }
 
 #[derive(Debug, Deserialize)]
@@ -195,16 +324,10 @@ struct SearchResponse {
   data: SearchResponseData,
 }
 
-#[derive(Debug, Serialize)]
-pub struct SearchResult {
-  total: u64,
-  lists: Vec<L1Track>,
-}
-
 #[derive(Debug, Deserialize)]
 struct Song {
-  hash: Option<String>,
   img: Option<String>,
+  play_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -216,45 +339,148 @@ struct SongResponse {
 
 #[async_trait]
 impl Provider for Kugou<'_> {
-  async fn get_playlists(&self, params: HashMap<String, String>) -> Vec<L1PlaylistInfo> {
-    let url = build_playlist_url(params);
-    let resp = self
-      .client
-      .get(url)
-      .send()
-      .await
-      .unwrap()
-      .json::<PlaylistsResponse>()
-      .await
-      .unwrap();
+  fn name(&self) -> &'static str {
+    PROVIDER
+  }
+
+  async fn get_playlists(
+    &self,
+    query: &PlaylistQuery,
+  ) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    self.fetch_playlists(query.into()).await
+  }
+
+  async fn get_playlist_detail(
+    &self,
+    playlist_id: &str,
+  ) -> Result<L1PlaylistDetail, ProviderError> {
+    let playlist_id: u64 = parse_param(PROVIDER, "playlist_id", playlist_id)?;
+    let url = endpoint_url(
+      &self.endpoints.mobile,
+      &format!("/plist/list/{playlist_id}?json=true"),
+    );
+
+    let (_, resp) = fetch_json::<PlaylistResponse>(PROVIDER, self.client.get(url)).await?;
+
+    let playlist = self.to_playlist(&resp.info.list);
+
+    let tracks = self.get_tracks(&resp.list.list).await?;
+    let detail = L1PlaylistDetail {
+      info: playlist,
+      tracks,
+    };
+
+    Ok(detail)
+  }
+
+  async fn search(&self, query: &SearchQuery) -> Result<L1SearchResult, ProviderError> {
+    self.search_songs(query.into()).await
+  }
+
+  async fn get_track_url(&self, track_id: &str) -> Result<L1TrackUrl, ProviderError> {
+    let song = self.get_song(track_id).await?;
+
+    match song.play_url {
+      Some(url) if !url.is_empty() => Ok(L1TrackUrl {
+        id: Source::Kugou.track(track_id),
+        url,
+      }),
+      _ => Err(ProviderError::parse(
+        PROVIDER,
+        self.build_song_url(track_id)?.as_str(),
+        "track has no playable url",
+      )),
+    }
+  }
+}
+
+impl<'a> Kugou<'a> {
+  pub fn new(client: &'a Client) -> Self {
+    Self::with_endpoints(client, Endpoints::default())
+  }
+
+  pub fn with_endpoints(client: &'a Client, endpoints: Endpoints) -> Self {
+    Kugou { client, endpoints }
+  }
+
+  pub async fn fetch_playlists(
+    &self,
+    params: PlaylistParams,
+  ) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    let url = params.build_url(&self.endpoints);
+    let (_, resp) = fetch_json::<PlaylistsResponse>(PROVIDER, self.client.get(url)).await?;
 
     let mut playlists: Vec<L1PlaylistInfo> = Vec::new();
 
     for item in resp.plist.list.info {
-      let playlist = L1PlaylistInfo {
-        id: "kgplaylist_".to_string() + &item.specialid.to_string(),
-        cover_img_url: {
-          item.imgurl.replace("{size}", "400")
-          // if item.imgurl.parse().unwrap() {
-          // } else {
-          //   String::from("")
-          // }
-        },
-        source_url: format!(
-          "https://www.kugou.com/yy/special/single/{specialid}.html",
-          specialid = item.specialid
-        ),
-        title: item.specialname,
-      };
+      let playlist = self.to_playlist(&item);
 
       playlists.push(playlist);
     }
 
-    return playlists;
+    Ok(playlists)
+  }
+
+  pub async fn search_songs(&self, params: SearchParams) -> Result<L1SearchResult, ProviderError> {
+    let search_url = params.build_url(&self.endpoints)?;
+
+    let (_, response) = fetch_json::<SearchResponse>(PROVIDER, self.client.get(search_url)).await?;
+
+    let tasks: Vec<_> = response
+      .data
+      .lists
+      .into_iter()
+      .map(|item| async move {
+        let bitrates = item.bitrates();
+        let mut track = L1Track {
+          album_id: Source::Kugou.album_ref(&item.album_id),
+          album: item.album_name,
+          source_url: format!(
+            "https://www.kugou.com/song/#hash={}&album_id={}",
+            item.file_hash, item.album_id
+          ),
+          duration: item.duration.map(|secs| secs * 1000),
+          release_date: item
+            .publish_date
+            .as_deref()
+            .and_then(parse_datetime)
+            .map(|x| x.date_naive()),
+          bitrates,
+          // url: format!("kgtrack_{}", item.file_hash),
+          // lyric_url: item.file_hash,
+          ..L1Track::new(Source::Kugou.track(&item.file_hash), item.song_name)
+        };
+        let singer_id = item.singer_id;
+        let singer_name = item.singer_name;
+        // if (item.SingerId instanceof Array) {
+        //   [singer_id] = singer_id;
+        //   [singer_name] = singer_name.split('、');
+        // }
+        track.artist = singer_name;
+        track.artist_id = singer_id
+          .first()
+          .and_then(|singer_id| Source::Kugou.artist_ref(singer_id.to_string()));
+
+        // the cover is cosmetic, so a failed lookup leaves it empty
+        track.img_url = self
+          .get_song(&item.file_hash)
+          .await
+          .ok()
+          .and_then(|song| song.img)
+          .unwrap_or_default();
+
+        track
+      })
+      .collect();
+
+    let tracks = futures::future::join_all(tasks).await;
+
+    Ok(L1SearchResult {
+      total: response.data.total,
+      result: tracks,
+    })
   }
-}
 
-impl Kugou<'_> {
   pub fn create_client() -> Client {
     let mut headers = header::HeaderMap::new();
     headers.insert(
@@ -271,23 +497,40 @@ impl Kugou<'_> {
   fn to_playlist(&self, playlist: &KugouPlaylistDetail) -> L1PlaylistInfo {
     L1PlaylistInfo {
       cover_img_url: playlist.imgurl.replace("{size}", "400"),
-      title: playlist.specialname.to_string(),
-      id: playlist.specialid.to_string(),
       source_url: format!(
         "https://www.kugou.com/yy/special/single/{}.html",
         playlist.specialid
       ),
+      description: playlist.intro.clone(),
+      creator_name: playlist.nickname.clone(),
+      creator_id: playlist.suid.clone(),
+      play_count: playlist.playcount,
+      track_count: playlist.songcount,
+      tags: playlist
+        .tags
+        .iter()
+        .map(|x| x.tagname.to_string())
+        .collect(),
+      created_at: playlist.publishtime.as_deref().and_then(parse_datetime),
+      ..L1PlaylistInfo::new(
+        Source::Kugou.playlist(playlist.specialid.to_string()),
+        &playlist.specialname,
+      )
     }
   }
 
-  async fn get_tracks(&self, items: &KugouPlaylistListInfo) -> Vec<L1Track> {
+  async fn get_tracks(&self, items: &KugouPlaylistListInfo) -> Result<Vec<L1Track>, ProviderError> {
     let tasks: Vec<_> = items
       .info
       .iter()
       .map(move |playlist_song| async {
         let mut track: L1Track = playlist_song.into();
-        let album_data = playlist_song.get_album(self.client).await;
-        let song_info = playlist_song.get_song_info(self.client).await;
+        let album_data = playlist_song
+          .get_album(self.client, &self.endpoints)
+          .await?;
+        let song_info = playlist_song
+          .get_song_info(self.client, &self.endpoints)
+          .await?;
 
         track.album = album_data.albumname;
         track.title = song_info.song_name;
@@ -295,59 +538,32 @@ impl Kugou<'_> {
           track.artist = String::from("未知")
         } else {
           track.artist = song_info.singer_name;
+          track.artist_id = Source::Kugou.artist_ref(song_info.singer_id.to_string());
         }
-        track.artist_id = format!("kgartist_{}", song_info.singer_id);
-        if song_info.album_img != "" {
+        if !song_info.album_img.is_empty() {
           track.img_url = song_info.album_img.replace("{size}", "400");
-        } else {
         }
 
-        track
+        Ok(track)
       })
       .collect();
 
-    futures::future::join_all(tasks).await
+    futures::future::try_join_all(tasks).await
   }
 
-  pub async fn get_playlist_detail(&self, playlist_id: &str) -> L1PlaylistDetail {
-    let url = format!("http://m.kugou.com/plist/list/{playlist_id}?json=true");
-
-    let resp = self
-      .client
-      .get(url)
-      .send()
-      .await
-      .unwrap()
-      .json::<PlaylistResponse>()
-      .await
-      .unwrap();
-
-    let playlist = self.to_playlist(&resp.info.list);
-
-    let tracks = self.get_tracks(&resp.list.list).await;
-    let detail = L1PlaylistDetail {
-      info: playlist,
-      tracks: tracks,
-    };
-
-    return detail;
+  fn build_song_url(&self, file_hash: &str) -> Result<Url, ProviderError> {
+    parse_endpoint_with_params(
+      PROVIDER,
+      &self.endpoints.web,
+      "/yy/index.php",
+      &[("r", "play/getdata"), ("hash", file_hash)],
+    )
   }
 
-  async fn get_song(&self, file_hash: &str) -> Song {
-    let url = format!(
-      "https://www.kugou.com/yy/index.php?r=play/getdata&hash={}",
-      file_hash
-    );
-    let response = self
-      .client
-      .get(&url)
-      .header(header::COOKIE, "kg_mid=3333")
-      .send()
-      .await
-      .unwrap()
-      .json::<SongResponse>()
-      .await
-      .unwrap();
+  async fn get_song(&self, file_hash: &str) -> Result<Song, ProviderError> {
+    let url = self.build_song_url(file_hash)?;
+    let request = self.client.get(url).header(header::COOKIE, "kg_mid=3333");
+    let (url, response) = fetch_json::<SongResponse>(PROVIDER, request).await?;
 
     // let response1 = self
     //   .client
@@ -360,73 +576,206 @@ impl Kugou<'_> {
     //   .await
     //   .unwrap();
 
-    println!("get song {:?}", response);
+    if response.status != 1 {
+      return Err(ProviderError::Api {
+        provider: PROVIDER,
+        url,
+        code: response.err_code.to_string(),
+        message: "play/getdata failed".to_string(),
+      });
+    }
 
-    response.data
+    Ok(response.data)
   }
+}
 
-  pub async fn search(&self, params: HashMap<String, String>) -> SearchResult {
-    let search_url = Url::parse_with_params(
-      "https://songsearch.kugou.com/song_search_v2",
-      Vec::from_iter(params.iter()),
-    )
-    .unwrap();
+#[cfg(test)]
+mod tests {
+  use crate::kugou::{Endpoints, Kugou};
+  use crate::media::{PlaylistQuery, Provider, ProviderError, SearchQuery};
+  use mockito::Matcher;
+
+  fn endpoints(server: &mockito::Server) -> Endpoints {
+    Endpoints {
+      mobile: server.url(),
+      mobile_cdn: server.url(),
+      search: server.url(),
+      web: server.url(),
+    }
+  }
 
-    let response = self
-      .client
-      .get(search_url)
-      .send()
-      .await
-      .unwrap()
-      .json::<SearchResponse>()
+  #[tokio::test]
+  async fn fetch_playlists() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+      .mock("GET", "/plist/index&json=true&page=1")
+      .with_body(include_str!("../fixtures/kugou/plist_index.json"))
+      .create_async()
+      .await;
+
+    let client = Kugou::create_client();
+    let kugou = Kugou::with_endpoints(&client, endpoints(&server));
+    let playlists = kugou
+      .fetch_playlists((&PlaylistQuery::default()).into())
       .await
       .unwrap();
 
-    let tasks: Vec<_> = response
-      .data
-      .lists
-      .into_iter()
-      .map(|item| async move {
-        let mut track = L1Track {
-          id: format!("kgtrack_{}", item.file_hash),
-          title: item.song_name,
-          artist: "".to_string(),
-          artist_id: "".to_string(),
-          album_id: format!("kgalbum_{}", item.album_id),
-          album: item.album_name,
-          source: "kugou".to_string(),
-          source_url: format!(
-            "https://www.kugou.com/song/#hash={}&album_id={}",
-            item.file_hash, item.album_id
-          ),
-          img_url: "".to_string(),
-          // url: format!("kgtrack_{}", item.file_hash),
-          url: None,
-          // lyric_url: item.file_hash,
-        };
-        let singer_id = item.singer_id;
-        let singer_name = item.singer_name;
-        // if (item.SingerId instanceof Array) {
-        //   [singer_id] = singer_id;
-        //   [singer_name] = singer_name.split('、');
-        // }
-        track.artist = singer_name;
-        track.artist_id = format!("kgartist_{}", singer_id[0]);
+    mock.assert_async().await;
+    assert_eq!(playlists.len(), 2);
+    let first = &playlists[0];
+    assert_eq!(first.id.to_string(), "kgplaylist_3952134");
+    assert!(first.cover_img_url.contains("/400/"));
+    assert_eq!(first.creator_name.as_deref(), Some("酷狗音乐人"));
+    assert_eq!(first.creator_id.as_deref(), Some("1275203416"));
+    assert_eq!(first.tags, vec!["伤感", "华语"]);
+    assert_eq!(
+      first.created_at.unwrap().to_string(),
+      "2022-12-30 00:00:00 UTC"
+    );
+    assert_eq!(playlists[1].creator_id.as_deref(), Some("98765"));
+    assert_eq!(playlists[1].description, None);
+  }
+
+  #[tokio::test]
+  async fn get_playlist_detail() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("GET", "/plist/list/3952134?json=true")
+      .with_body(include_str!("../fixtures/kugou/plist_list.json"))
+      .create_async()
+      .await;
+    server
+      .mock("GET", "/api/v3/album/info?albumid=40135870")
+      .with_body(include_str!("../fixtures/kugou/album_info.json"))
+      .create_async()
+      .await;
+    server
+      .mock("GET", Matcher::Regex("^/app/i/getSongInfo.php".into()))
+      .match_query(Matcher::UrlEncoded(
+        "hash".into(),
+        "C2E7E1E1C6C8A2AF85C5A0B4C4D0E20A".into(),
+      ))
+      .with_body(include_str!("../fixtures/kugou/song_info.json"))
+      .create_async()
+      .await;
+    server
+      .mock("GET", Matcher::Regex("^/app/i/getSongInfo.php".into()))
+      .match_query(Matcher::UrlEncoded(
+        "hash".into(),
+        "9DC1E1F1B0C8A2AF85C5A0B4C4D0E77B".into(),
+      ))
+      .with_body(r#"{"songName":"","singerName":"","singerId":0,"album_img":""}"#)
+      .create_async()
+      .await;
 
-        let song = self.get_song(&item.file_hash).await;
-        track.img_url = song.img.unwrap_or_else(|| "".into());
+    let client = Kugou::create_client();
+    let kugou = Kugou::with_endpoints(&client, endpoints(&server));
+    let detail = kugou.get_playlist_detail("3952134").await.unwrap();
 
-        track
-      })
-      .collect();
+    assert_eq!(detail.info.title, "伤感华语：一个人的心事无人懂");
+    assert_eq!(detail.tracks.len(), 2);
 
-    let tracks = futures::future::join_all(tasks).await;
+    let track = &detail.tracks[0];
+    assert_eq!(
+      track.id.to_string(),
+      "kgtrack_C2E7E1E1C6C8A2AF85C5A0B4C4D0E20A"
+    );
+    assert_eq!(track.title, "大鱼");
+    assert_eq!(track.album, "大鱼");
+    assert_eq!(track.artist_id.as_ref().unwrap().id, "169967");
+    assert!(track.img_url.contains("/400/"));
+
+    // the second track is a single without a known singer
+    let track = &detail.tracks[1];
+    assert_eq!(track.album, "");
+    assert_eq!(track.artist, "未知");
+    assert!(track.album_id.is_none());
+  }
 
-    println!("kugou searched tracks length is {:?}", tracks.len());
+  #[tokio::test]
+  async fn get_playlist_detail_song_info_error() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("GET", "/plist/list/3952134?json=true")
+      .with_body(include_str!("../fixtures/kugou/plist_list.json"))
+      .create_async()
+      .await;
+    server
+      .mock("GET", "/api/v3/album/info?albumid=40135870")
+      .with_body(include_str!("../fixtures/kugou/album_info.json"))
+      .create_async()
+      .await;
+    server
+      .mock("GET", Matcher::Regex("^/app/i/getSongInfo.php".into()))
+      .with_status(500)
+      .create_async()
+      .await;
 
-    SearchResult {
-      total: response.data.total,
-      lists: tracks,
-    }
+    let client = Kugou::create_client();
+    let kugou = Kugou::with_endpoints(&client, endpoints(&server));
+    let err = kugou.get_playlist_detail("3952134").await.unwrap_err();
+
+    assert!(matches!(err, ProviderError::Status { .. }));
+  }
+
+  #[tokio::test]
+  async fn ids_are_not_spliced_into_urls() {
+    let mut server = mockito::Server::new_async().await;
+    let song = server
+      .mock("GET", "/yy/index.php")
+      .match_query(Matcher::AllOf(vec![
+        Matcher::UrlEncoded("r".into(), "play/getdata".into()),
+        Matcher::UrlEncoded("hash".into(), "ABC&r=other".into()),
+      ]))
+      .with_body(r#"{"status":1,"err_code":0,"data":{"img":"","play_url":"http://x/a.mp3"}}"#)
+      .create_async()
+      .await;
+
+    let client = Kugou::create_client();
+    let kugou = Kugou::with_endpoints(&client, endpoints(&server));
+    let url = kugou.get_track_url("ABC&r=other").await.unwrap();
+
+    song.assert_async().await;
+    assert_eq!(url.url, "http://x/a.mp3");
+    assert!(matches!(
+      kugou.get_playlist_detail("1?x=y").await,
+      Err(ProviderError::InvalidParameter { .. })
+    ));
+  }
+
+  #[tokio::test]
+  async fn search_keeps_tracks_without_cover() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("GET", "/song_search_v2")
+      .match_query(Matcher::Any)
+      .with_body(
+        r#"{"data":{"total":2,"lists":[
+          {"FileHash":"AAA","SongName":"大鱼","AlbumName":"大鱼","AlbumID":"1","SingerId":[169967],"SingerName":"周深"},
+          {"FileHash":"BBB","SongName":"小鱼","AlbumName":"","AlbumID":"","SingerId":[],"SingerName":""}]}}"#,
+      )
+      .create_async()
+      .await;
+    server
+      .mock("GET", "/yy/index.php")
+      .match_query(Matcher::UrlEncoded("hash".into(), "AAA".into()))
+      .with_body(r#"{"status":1,"err_code":0,"data":{"img":"http://x/a.jpg","play_url":""}}"#)
+      .create_async()
+      .await;
+    server
+      .mock("GET", "/yy/index.php")
+      .match_query(Matcher::UrlEncoded("hash".into(), "BBB".into()))
+      .with_body(r#"{"status":0,"err_code":20010,"data":[]}"#)
+      .create_async()
+      .await;
+
+    let client = Kugou::create_client();
+    let kugou = Kugou::with_endpoints(&client, endpoints(&server));
+    let result = kugou.search(&SearchQuery::new("大鱼")).await.unwrap();
+
+    assert_eq!(result.total, 2);
+    assert_eq!(result.result[0].img_url, "http://x/a.jpg");
+    assert_eq!(result.result[1].title, "小鱼");
+    assert_eq!(result.result[1].img_url, "");
   }
 }
@@ -1,13 +1,42 @@
+use crate::id::Source;
+use crate::media::{
+  Bitrate, L1SearchResult, L1Track, L1TrackUrl, Provider, ProviderError, SearchQuery,
+};
+use crate::utils::{
+  endpoint_url, fetch_json, fetch_text, parse_datetime, parse_endpoint_with_params, parse_param,
+  require_param, send,
+};
+use async_trait::async_trait;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use url::Url;
 
-const HOST: &'static str = "https://www.kuwo.cn";
-const SONG_URL: &'static str = "https://antiserver.kuwo.cn/anti.s";
+const PROVIDER: &str = "kuwo";
+
+const HOST: &str = "https://www.kuwo.cn";
+const ANTI_HOST: &str = "https://antiserver.kuwo.cn";
+const SEARCH_PATH: &str = "/api/www/search/searchMusicBykeyWord";
+const SONG_PATH: &str = "/anti.s";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+  pub api: String,
+  pub anti: String,
+}
+
+impl Default for Endpoints {
+  fn default() -> Self {
+    Self {
+      api: HOST.to_string(),
+      anti: ANTI_HOST.to_string(),
+    }
+  }
+}
 
 pub struct Kuwo<'a> {
   pub client: &'a Client,
+  pub endpoints: Endpoints,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -19,6 +48,19 @@ pub struct SearchedSong {
   pub album: String,
   pub albumid: String,
   pub pic: String,
+  #[serde(default)]
+  pub duration: Option<u64>,
+  #[serde(default)]
+  pub track: Option<u32>,
+  #[serde(default, rename = "releaseDate")]
+  pub release_date: Option<String>,
+  #[serde(default, rename = "hasLossless")]
+  pub has_lossless: bool,
+  /// Available files, e.g. `level:p,bitrate:320,format:mp3,size:10.27Mb;...`.
+  #[serde(default)]
+  pub minfo: Option<String>,
+  #[serde(default, rename = "isListenFee")]
+  pub is_listen_fee: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -32,12 +74,157 @@ pub struct SearchResponse {
   pub data: SearchResult,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchParams {
+  pub keyword: String,
+  pub page: u32,
+  pub page_size: u32,
+}
+
+impl Default for SearchParams {
+  fn default() -> Self {
+    Self {
+      keyword: "".to_string(),
+      page: 1,
+      page_size: 30,
+    }
+  }
+}
+
+impl From<&SearchQuery> for SearchParams {
+  fn from(query: &SearchQuery) -> Self {
+    Self {
+      keyword: query.keyword.to_string(),
+      page: query.pagination.page,
+      page_size: query.pagination.page_size,
+    }
+  }
+}
+
+impl SearchParams {
+  pub fn from_query(query: &HashMap<String, String>) -> Result<Self, ProviderError> {
+    let keyword = require_param(PROVIDER, query, "keyword")?;
+    let page = require_param(PROVIDER, query, "curpage")?;
+
+    Ok(Self {
+      keyword: keyword.to_string(),
+      page: parse_param(PROVIDER, "curpage", page)?,
+      ..Default::default()
+    })
+  }
+
+  pub fn build_url(&self, endpoints: &Endpoints) -> Result<Url, ProviderError> {
+    parse_endpoint_with_params(
+      PROVIDER,
+      &endpoints.api,
+      SEARCH_PATH,
+      &[
+        ("key", self.keyword.to_string()),
+        ("pn", self.page.to_string()),
+        ("rn", self.page_size.to_string()),
+      ],
+    )
+  }
+}
+
+impl SearchedSong {
+  pub fn bitrates(&self) -> Vec<Bitrate> {
+    let mut bitrates: Vec<Bitrate> = self
+      .minfo
+      .iter()
+      .flat_map(|x| x.split(';'))
+      .filter_map(|file| {
+        let fields: HashMap<&str, &str> =
+          file.split(',').filter_map(|x| x.split_once(':')).collect();
+        match (fields.get("format")?, fields.get("bitrate")?) {
+          (&"flac" | &"ape", _) => Some(Bitrate::Lossless),
+          (&"mp3", &"320") => Some(Bitrate::K320),
+          (&"mp3", &"192") => Some(Bitrate::K192),
+          (&"mp3", &"128") => Some(Bitrate::K128),
+          _ => None,
+        }
+      })
+      .collect();
+    if self.minfo.is_none() && self.has_lossless {
+      bitrates.push(Bitrate::Lossless);
+    }
+    bitrates.sort_by_key(|x| x.kbps().unwrap_or(u32::MAX));
+    bitrates.dedup();
+
+    bitrates
+  }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UrlResponse {
   pub data: String,
 }
 
-impl Kuwo<'_> {
+#[async_trait]
+impl Provider for Kuwo<'_> {
+  fn name(&self) -> &'static str {
+    PROVIDER
+  }
+
+  async fn search(&self, query: &SearchQuery) -> Result<L1SearchResult, ProviderError> {
+    let response = self.search_song_by_keyword(query.into()).await?;
+
+    let result = response
+      .data
+      .list
+      .iter()
+      .map(|song| L1Track {
+        artist: song.artist.to_string(),
+        artist_id: Source::Kuwo.artist_ref(song.artistid.to_string()),
+        album_id: Source::Kuwo.album_ref(&song.albumid),
+        album: song.album.to_string(),
+        source_url: format!("{}/play_detail/{}", HOST, song.rid),
+        img_url: song.pic.to_string(),
+        duration: song.duration.map(|secs| secs * 1000),
+        track_number: song.track.filter(|x| *x > 0),
+        release_date: song
+          .release_date
+          .as_deref()
+          .and_then(parse_datetime)
+          // kuwo sends 1970-01-01 for an unknown date
+          .filter(|x| x.timestamp() != 0)
+          .map(|x| x.date_naive()),
+        bitrates: song.bitrates(),
+        vip: song.is_listen_fee,
+        ..L1Track::new(Source::Kuwo.track(song.rid.to_string()), &song.name)
+      })
+      .collect();
+
+    let total = response.data.total.parse().map_err(|_| {
+      ProviderError::parse(
+        PROVIDER,
+        &endpoint_url(&self.endpoints.api, SEARCH_PATH),
+        format!("invalid search total {:?}", response.data.total),
+      )
+    })?;
+
+    Ok(L1SearchResult { total, result })
+  }
+
+  async fn get_track_url(&self, track_id: &str) -> Result<L1TrackUrl, ProviderError> {
+    let url = self.get_track(track_id).await?;
+
+    Ok(L1TrackUrl {
+      id: Source::Kuwo.track(track_id),
+      url,
+    })
+  }
+}
+
+impl<'a> Kuwo<'a> {
+  pub fn new(client: &'a Client) -> Self {
+    Self::with_endpoints(client, Endpoints::default())
+  }
+
+  pub fn with_endpoints(client: &'a Client, endpoints: Endpoints) -> Self {
+    Kuwo { client, endpoints }
+  }
+
   pub fn create_client() -> Client {
     let mut headers = header::HeaderMap::new();
     headers.insert("Referer", header::HeaderValue::from_static(HOST));
@@ -50,86 +237,141 @@ impl Kuwo<'_> {
       .unwrap()
   }
 
-  pub async fn get_cookie(&self) -> String {
-    let resp: HashMap<String, String> = self
-      .client
-      .head("https://www.kuwo.cn")
-      .send()
-      .await
-      .unwrap()
+  pub async fn get_cookie(&self) -> Result<String, ProviderError> {
+    let (url, response) = send(PROVIDER, self.client.head(&self.endpoints.api)).await?;
+    let resp: HashMap<String, String> = response
       .cookies()
       .map(|i| (i.name().to_string(), i.value().to_string()))
       .collect();
 
-    println!("refreshed kw_token is {}", resp.get("kw_token").unwrap());
-    resp.get("kw_token").unwrap().to_string()
+    let token = resp
+      .get("kw_token")
+      .ok_or_else(|| ProviderError::parse(PROVIDER, &url, "kw_token cookie is missing"))?;
+
+    Ok(token.to_string())
   }
 
-  pub async fn search(&self, params: HashMap<String, String>) -> SearchResponse {
-    match params.get("api") {
-      Some(val) => {
-        if val == "searchMusicBykeyWord" {
-          self.search_song_by_keyword(params).await
-        } else {
-          self.search_song_by_keyword(params).await
-        }
-      }
-      _ => self.search_song_by_keyword(params).await,
-    }
+  pub async fn search_song_by_keyword(
+    &self,
+    params: SearchParams,
+  ) -> Result<SearchResponse, ProviderError> {
+    let token = self.get_cookie().await?;
+
+    let url = params.build_url(&self.endpoints)?;
+    let (_, response) = fetch_json(PROVIDER, self.client.get(url).header("CSRF", token)).await?;
+
+    Ok(response)
   }
 
-  pub async fn search_song_by_keyword(&self, params: HashMap<String, String>) -> SearchResponse {
-    let token = self.get_cookie().await;
+  pub async fn get_track(&self, song_id: &str) -> Result<String, ProviderError> {
+    let url = self.build_track_url(song_id)?;
+    let token = self.get_cookie().await?;
 
-    let mut items: Vec<(String, String)> = vec![];
-    let keyword = params.get("keyword").unwrap().to_string();
-    let page_number = params.get("curpage").unwrap().parse().unwrap();
-    items.push(("key".to_string(), keyword));
-    items.push(("pn".to_string(), page_number));
+    let (_, song_url) = fetch_text(PROVIDER, self.client.get(url).header("CSRF", token)).await?;
 
-    let url = format!("{}/api/www/search/searchMusicBykeyWord", HOST);
-    let url = Url::parse_with_params(&url, &items).unwrap();
-    let response = self
-      .client
-      .get(url)
-      .header("CSRF", token)
-      .send()
-      .await
-      .unwrap()
-      .json::<SearchResponse>()
-      .await
-      .unwrap();
+    Ok(song_url)
+  }
 
-    response
+  pub fn build_track_url(&self, song_id: &str) -> Result<Url, ProviderError> {
+    parse_endpoint_with_params(
+      PROVIDER,
+      &self.endpoints.anti,
+      SONG_PATH,
+      &[
+        ("type", "convert_url"),
+        ("format", "mp3"),
+        ("response", "url"),
+        ("rid", song_id),
+      ],
+    )
   }
+}
 
-  pub async fn get_track(&self, song_id: &str) -> String {
-    let url = Kuwo::build_track_url(song_id);
-    let token = self.get_cookie().await;
+#[cfg(test)]
+mod tests {
+  use crate::kuwo::{Endpoints, Kuwo, SearchedSong};
+  use crate::media::{Bitrate, Provider, SearchQuery};
+  use mockito::Matcher;
 
-    let song_url = self
-      .client
-      .get(url)
-      .header("CSRF", token)
-      .send()
-      .await
-      .unwrap()
-      .text()
-      .await
-      .unwrap();
+  #[tokio::test]
+  async fn search() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("HEAD", "/")
+      .with_header("set-cookie", "kw_token=QCZ4KF2BA4E; path=/")
+      .create_async()
+      .await;
+    let mock = server
+      .mock(
+        "GET",
+        Matcher::Regex("^/api/www/search/searchMusicBykeyWord".into()),
+      )
+      .match_header("csrf", "QCZ4KF2BA4E")
+      .match_query(Matcher::UrlEncoded("key".into(), "周杰伦".into()))
+      .with_body(include_str!("../fixtures/kuwo/search.json"))
+      .create_async()
+      .await;
+
+    let client = Kuwo::create_client();
+    let kuwo = Kuwo::with_endpoints(
+      &client,
+      Endpoints {
+        api: server.url(),
+        anti: server.url(),
+      },
+    );
+    let result = kuwo.search(&SearchQuery::new("周杰伦")).await.unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(result.total, 1836);
+    assert_eq!(result.result.len(), 2);
+
+    let track = &result.result[0];
+    assert_eq!(track.id.to_string(), "kwtrack_228908");
+    assert_eq!(track.title, "晴天");
+    assert_eq!(track.artist_id.as_ref().unwrap().id, "336");
+    assert_eq!(track.duration, Some(269_000));
+    assert_eq!(track.track_number, Some(3));
+    assert_eq!(track.release_date.unwrap().to_string(), "2003-07-31");
+    assert_eq!(track.bitrates.last(), Some(&Bitrate::Lossless));
+    assert!(track.vip);
+
+    let track = &result.result[1];
+    assert!(!track.vip);
+    assert!(track.album_id.is_none());
+    assert!(track.release_date.is_none());
+    assert!(track.bitrates.is_empty());
+  }
+
+  #[test]
+  fn bitrates_from_minfo() {
+    let song: SearchedSong = serde_json::from_str(
+      r#"{"rid":228908,"name":"晴天","artist":"周杰伦","artistid":336,"album":"叶惠美",
+        "albumid":"1124","pic":"","hasLossless":true,
+        "minfo":"level:ff,bitrate:2000,format:flac,size:29.39Mb;level:p,bitrate:320,format:mp3,size:10.27Mb;level:h,bitrate:128,format:mp3,size:4.11Mb;level:s,bitrate:48,format:aac,size:1.54Mb"}"#,
+    )
+    .unwrap();
 
-    song_url
+    assert_eq!(
+      song.bitrates(),
+      vec![Bitrate::K128, Bitrate::K320, Bitrate::Lossless]
+    );
   }
 
-  pub fn build_track_url(song_id: &str) -> String {
-    let mut params: HashMap<String, String> = HashMap::new();
-    params.insert("type".to_string(), "convert_url".to_string());
-    params.insert("format".to_string(), "mp3".to_string());
-    params.insert("response".to_string(), "url".to_string());
-    params.insert("rid".to_string(), song_id.to_string());
+  #[tokio::test]
+  async fn search_without_token() {
+    let mut server = mockito::Server::new_async().await;
+    server.mock("HEAD", "/").create_async().await;
 
-    let url = Url::parse_with_params(SONG_URL, &params).unwrap();
+    let client = Kuwo::create_client();
+    let kuwo = Kuwo::with_endpoints(
+      &client,
+      Endpoints {
+        api: server.url(),
+        anti: server.url(),
+      },
+    );
 
-    return url.to_string();
+    assert!(kuwo.search(&SearchQuery::new("周杰伦")).await.is_err());
   }
 }
@@ -1,3 +1,5 @@
+pub mod dispatcher;
+pub mod id;
 pub mod kugou;
 pub mod kuwo;
 pub mod media;
@@ -6,3 +8,6 @@ pub mod netease;
 pub mod qq;
 
 mod utils;
+
+pub use dispatcher::{Listen1, ProviderEndpoints};
+pub use id::{IdKind, L1Id, Source};
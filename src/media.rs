@@ -1,48 +1,364 @@
+use crate::id::{L1Id, Source};
 use crate::kugou::KugouSongInPlaylist;
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum ProviderError {
+  Transport {
+    provider: &'static str,
+    url: String,
+    source: reqwest::Error,
+  },
+  Status {
+    provider: &'static str,
+    url: String,
+    status: StatusCode,
+  },
+  Decode {
+    provider: &'static str,
+    url: String,
+    message: String,
+  },
+  Api {
+    provider: &'static str,
+    url: String,
+    code: String,
+    message: String,
+  },
+  Parse {
+    provider: &'static str,
+    url: String,
+    message: String,
+  },
+  Blocked {
+    provider: &'static str,
+    url: String,
+  },
+  Empty {
+    provider: &'static str,
+    url: String,
+  },
+  MissingParameter {
+    provider: &'static str,
+    name: String,
+  },
+  InvalidParameter {
+    provider: &'static str,
+    name: String,
+    value: String,
+  },
+  Unsupported {
+    provider: &'static str,
+    operation: &'static str,
+  },
+}
+
+impl ProviderError {
+  pub fn provider(&self) -> &'static str {
+    match self {
+      ProviderError::Transport { provider, .. }
+      | ProviderError::Status { provider, .. }
+      | ProviderError::Decode { provider, .. }
+      | ProviderError::Api { provider, .. }
+      | ProviderError::Parse { provider, .. }
+      | ProviderError::Blocked { provider, .. }
+      | ProviderError::Empty { provider, .. }
+      | ProviderError::MissingParameter { provider, .. }
+      | ProviderError::InvalidParameter { provider, .. }
+      | ProviderError::Unsupported { provider, .. } => provider,
+    }
+  }
+
+  pub fn url(&self) -> Option<&str> {
+    match self {
+      ProviderError::Transport { url, .. }
+      | ProviderError::Status { url, .. }
+      | ProviderError::Decode { url, .. }
+      | ProviderError::Api { url, .. }
+      | ProviderError::Parse { url, .. }
+      | ProviderError::Blocked { url, .. }
+      | ProviderError::Empty { url, .. } => Some(url),
+      ProviderError::MissingParameter { .. }
+      | ProviderError::InvalidParameter { .. }
+      | ProviderError::Unsupported { .. } => None,
+    }
+  }
+
+  pub(crate) fn parse(provider: &'static str, url: &str, message: impl Into<String>) -> Self {
+    ProviderError::Parse {
+      provider,
+      url: url.to_string(),
+      message: message.into(),
+    }
+  }
+
+  pub(crate) fn missing(provider: &'static str, name: &str) -> Self {
+    ProviderError::MissingParameter {
+      provider,
+      name: name.to_string(),
+    }
+  }
+
+  pub(crate) fn unsupported(provider: &'static str, operation: &'static str) -> Self {
+    ProviderError::Unsupported {
+      provider,
+      operation,
+    }
+  }
+
+  pub(crate) fn invalid(provider: &'static str, name: &str, value: &str) -> Self {
+    ProviderError::InvalidParameter {
+      provider,
+      name: name.to_string(),
+      value: value.to_string(),
+    }
+  }
+}
+
+impl Display for ProviderError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ProviderError::Transport {
+        provider,
+        url,
+        source,
+      } => write!(f, "[{provider}] request to {url} failed: {source}"),
+      ProviderError::Status {
+        provider,
+        url,
+        status,
+      } => write!(f, "[{provider}] {url} responded with {status}"),
+      ProviderError::Decode {
+        provider,
+        url,
+        message,
+      } => write!(f, "[{provider}] cannot decode response of {url}: {message}"),
+      ProviderError::Api {
+        provider,
+        url,
+        code,
+        message,
+      } => write!(f, "[{provider}] {url} returned api code {code}: {message}"),
+      ProviderError::Parse {
+        provider,
+        url,
+        message,
+      } => write!(f, "[{provider}] cannot parse content of {url}: {message}"),
+      ProviderError::Blocked { provider, url } => {
+        write!(f, "[{provider}] {url} answered with an anti-bot page")
+      }
+      ProviderError::Empty { provider, url } => write!(f, "[{provider}] {url} returned nothing"),
+      ProviderError::MissingParameter { provider, name } => {
+        write!(f, "[{provider}] missing parameter `{name}`")
+      }
+      ProviderError::InvalidParameter {
+        provider,
+        name,
+        value,
+      } => write!(
+        f,
+        "[{provider}] invalid value {value:?} for parameter `{name}`"
+      ),
+      ProviderError::Unsupported {
+        provider,
+        operation,
+      } => write!(f, "[{provider}] {operation} is not supported"),
+    }
+  }
+}
+
+impl Error for ProviderError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      ProviderError::Transport { source, .. } => Some(source),
+      _ => None,
+    }
+  }
+}
 
 #[derive(Debug, Serialize)]
 pub struct L1PlaylistInfo {
-  pub id: String,
+  pub id: L1Id,
   pub cover_img_url: String,
   pub source_url: String,
   pub title: String,
+  pub description: Option<String>,
+  pub creator_name: Option<String>,
+  pub creator_id: Option<String>,
+  pub play_count: Option<u64>,
+  pub track_count: Option<u64>,
+  pub tags: Vec<String>,
+  pub created_at: Option<DateTime<Utc>>,
+  pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl L1PlaylistInfo {
+  pub fn new(id: L1Id, title: impl Into<String>) -> Self {
+    L1PlaylistInfo {
+      id,
+      cover_img_url: "".to_string(),
+      source_url: "".to_string(),
+      title: title.into(),
+      description: None,
+      creator_name: None,
+      creator_id: None,
+      play_count: None,
+      track_count: None,
+      tags: vec![],
+      created_at: None,
+      updated_at: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Bitrate {
+  #[serde(rename = "128k")]
+  K128,
+  #[serde(rename = "192k")]
+  K192,
+  #[serde(rename = "320k")]
+  K320,
+  #[serde(rename = "lossless")]
+  Lossless,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Bitrate {
+  pub fn kbps(&self) -> Option<u32> {
+    match self {
+      Bitrate::K128 => Some(128),
+      Bitrate::K192 => Some(192),
+      Bitrate::K320 => Some(320),
+      Bitrate::Lossless => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1TrackArtist {
+  pub id: Option<L1Id>,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct L1Track {
-  pub id: String,
+  pub id: L1Id,
+  #[serde(default)]
+  pub id2: Option<L1Id>,
   pub title: String,
+  /// Display string: every artist name joined with " / ".
   pub artist: String,
-  pub artist_id: String,
-  pub album_id: String,
+  pub artist_id: Option<L1Id>,
+  /// All credited artists in order, empty when the source only gives `artist`.
+  #[serde(default)]
+  pub artists: Vec<L1TrackArtist>,
+  pub album_id: Option<L1Id>,
   pub album: String,
-  pub source: String,
+  pub source: Source,
   pub source_url: String,
   pub img_url: String,
   pub url: Option<String>,
+  #[serde(default)]
+  pub lyric_url: Option<String>,
+  #[serde(default)]
+  pub tlyric_url: Option<String>,
+  #[serde(default)]
+  pub quality: Option<String>,
+  #[serde(default = "default_playable")]
+  pub playable: bool,
+  /// Length in milliseconds.
+  #[serde(default)]
+  pub duration: Option<u64>,
+  #[serde(default)]
+  pub disc_number: Option<u32>,
+  #[serde(default)]
+  pub track_number: Option<u32>,
+  #[serde(default)]
+  pub release_date: Option<NaiveDate>,
+  #[serde(default)]
+  pub bitrates: Vec<Bitrate>,
+  #[serde(default)]
+  pub vip: bool,
+}
+
+fn default_playable() -> bool {
+  true
+}
+
+impl L1Track {
+  pub fn new(id: L1Id, title: impl Into<String>) -> Self {
+    L1Track {
+      source: id.source,
+      id,
+      id2: None,
+      title: title.into(),
+      artist: "".to_string(),
+      artist_id: None,
+      artists: vec![],
+      album_id: None,
+      album: "".to_string(),
+      source_url: "".to_string(),
+      img_url: "".to_string(),
+      url: None,
+      lyric_url: None,
+      tlyric_url: None,
+      quality: None,
+      playable: true,
+      duration: None,
+      disc_number: None,
+      track_number: None,
+      release_date: None,
+      bitrates: vec![],
+      vip: false,
+    }
+  }
+
+  /// Sets `artists`, the joined `artist` string and the first artist's id.
+  pub fn with_artists(self, artists: Vec<L1TrackArtist>) -> Self {
+    L1Track {
+      artist: artists
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" / "),
+      artist_id: artists.first().and_then(|x| x.id.clone()),
+      artists,
+      ..self
+    }
+  }
 }
 
 impl From<&KugouSongInPlaylist> for L1Track {
   fn from(item: &KugouSongInPlaylist) -> Self {
     L1Track {
-      id: format!("kgtrack_{}", item.hash),
-      title: String::from(""),
-      artist: String::from(""),
-      artist_id: String::from(""),
-      album: String::from(""),
-      album_id: format!("kgalbum_{}", item.album_id),
-      source: "".to_string(),
+      album_id: Source::Kugou.album_ref(&item.album_id),
       source_url: item.build_track_source_url(),
-      img_url: String::from(""),
-      url: Some(String::from("")),
       // lyric_url: item.hash
+      ..L1Track::new(Source::Kugou.track(&item.hash), "")
     }
   }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct L1PlaylistCategory {
+  /// Value to pass as `PlaylistQuery::category_id`.
+  pub id: String,
+  pub name: String,
+  pub hot: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct L1CategoryGroup {
+  pub name: String,
+  pub categories: Vec<L1PlaylistCategory>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct L1PlaylistDetail {
   pub info: L1PlaylistInfo,
@@ -50,22 +366,313 @@ pub struct L1PlaylistDetail {
 }
 
 #[derive(Debug, Serialize)]
-pub struct L1Song {
-  pub id: String,
-  pub id2: String,
+pub struct L1SearchResult<T = L1Track> {
+  pub total: u64,
+  pub result: Vec<T>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct L1TrackUrl {
+  pub id: L1Id,
+  pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct L1Stream {
+  pub id: L1Id,
+  pub url: String,
+  /// Actual bitrate in kbps, which may be lower than the one asked for.
+  pub bitrate: Option<u32>,
+  pub size: Option<u64>,
+  pub format: Option<String>,
+  pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnavailableReason {
+  VipOnly,
+  NoCopyright,
+  NotFound,
+}
+
+impl UnavailableReason {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      UnavailableReason::VipOnly => "vip_only",
+      UnavailableReason::NoCopyright => "no_copyright",
+      UnavailableReason::NotFound => "not_found",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum L1TrackStream {
+  Available(L1Stream),
+  Unavailable { id: L1Id, reason: UnavailableReason },
+}
+
+#[derive(Debug, Serialize)]
+pub struct L1Lyrics {
+  pub lyric: String,
+  pub tlyric: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct L1AlbumInfo {
+  pub id: L1Id,
   pub title: String,
   pub artist: String,
-  pub artist_id: String,
-  pub album: String,
-  pub album_id: String,
-  pub img_url: String,
-  pub source: String,
+  pub artist_id: Option<L1Id>,
+  pub cover_img_url: String,
   pub source_url: String,
-  pub url: Option<String>,
+  pub description: Option<String>,
+  pub release_date: Option<NaiveDate>,
+  pub track_count: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct L1AlbumDetail {
+  pub info: L1AlbumInfo,
+  pub tracks: Vec<L1Track>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct L1ArtistInfo {
+  pub id: L1Id,
+  pub name: String,
+  pub cover_img_url: String,
+  pub source_url: String,
+  pub description: Option<String>,
+  pub album_count: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct L1ArtistDetail {
+  pub info: L1ArtistInfo,
+  pub tracks: Vec<L1Track>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+  pub page: u32,
+  pub page_size: u32,
+}
+
+impl Default for Pagination {
+  fn default() -> Self {
+    Self {
+      page: 1,
+      page_size: 30,
+    }
+  }
+}
+
+impl Pagination {
+  pub fn new(page: u32, page_size: u32) -> Self {
+    Self { page, page_size }
+  }
+
+  pub fn offset(&self) -> u32 {
+    self.page.saturating_sub(1) * self.page_size
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaylistQuery {
+  pub category_id: Option<String>,
+  pub pagination: Pagination,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+  pub keyword: String,
+  pub pagination: Pagination,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchType {
+  #[default]
+  Song,
+  Playlist,
+  Album,
+  Artist,
+}
+
+impl SearchType {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      SearchType::Song => "song",
+      SearchType::Playlist => "playlist",
+      SearchType::Album => "album",
+      SearchType::Artist => "artist",
+    }
+  }
+}
+
+impl FromStr for SearchType {
+  type Err = ProviderError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    [
+      SearchType::Song,
+      SearchType::Playlist,
+      SearchType::Album,
+      SearchType::Artist,
+    ]
+    .into_iter()
+    .find(|x| x.as_str() == s)
+    .ok_or_else(|| ProviderError::invalid("listen1", "type", s))
+  }
 }
 
+impl SearchQuery {
+  pub fn new(keyword: impl Into<String>) -> Self {
+    Self {
+      keyword: keyword.into(),
+      ..Default::default()
+    }
+  }
+}
+
+/// Common surface of every music source.
+///
+/// Ids passed in are the upstream part of an `L1Id`
+/// (`"123"`, not `"neplaylist_123"`). Operations a source cannot serve
+/// fail with `ProviderError::Unsupported`.
 #[async_trait]
-pub trait Provider {
-  async fn get_playlists(&self, params: HashMap<String, String>) -> Vec<L1PlaylistInfo>;
-  // async fn get_playlist_detail(playlist_id: &str) {}
+pub trait Provider: Send + Sync {
+  fn name(&self) -> &'static str;
+
+  async fn get_playlists(
+    &self,
+    _query: &PlaylistQuery,
+  ) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "get_playlists"))
+  }
+
+  async fn get_playlist_categories(&self) -> Result<Vec<L1CategoryGroup>, ProviderError> {
+    Err(ProviderError::unsupported(
+      self.name(),
+      "get_playlist_categories",
+    ))
+  }
+
+  async fn get_toplists(&self) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "get_toplists"))
+  }
+
+  async fn get_playlist_detail(
+    &self,
+    _playlist_id: &str,
+  ) -> Result<L1PlaylistDetail, ProviderError> {
+    Err(ProviderError::unsupported(
+      self.name(),
+      "get_playlist_detail",
+    ))
+  }
+
+  async fn search(&self, _query: &SearchQuery) -> Result<L1SearchResult, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "search"))
+  }
+
+  async fn search_playlists(
+    &self,
+    _query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1PlaylistInfo>, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "search_playlists"))
+  }
+
+  async fn search_albums(
+    &self,
+    _query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1AlbumInfo>, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "search_albums"))
+  }
+
+  async fn search_artists(
+    &self,
+    _query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1ArtistInfo>, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "search_artists"))
+  }
+
+  async fn get_track_url(&self, _track_id: &str) -> Result<L1TrackUrl, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "get_track_url"))
+  }
+
+  async fn get_track_stream(
+    &self,
+    _track_id: &str,
+    _bitrate: Bitrate,
+  ) -> Result<L1TrackStream, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "get_track_stream"))
+  }
+
+  async fn get_lyrics(&self, _track_id: &str) -> Result<L1Lyrics, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "get_lyrics"))
+  }
+
+  async fn get_album(&self, _album_id: &str) -> Result<L1AlbumDetail, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "get_album"))
+  }
+
+  async fn get_artist(&self, _artist_id: &str) -> Result<L1ArtistDetail, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "get_artist"))
+  }
+
+  async fn get_artist_albums(
+    &self,
+    _artist_id: &str,
+    _pagination: &Pagination,
+  ) -> Result<L1SearchResult<L1AlbumInfo>, ProviderError> {
+    Err(ProviderError::unsupported(self.name(), "get_artist_albums"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::id::Source;
+  use crate::kugou::KugouSongInPlaylist;
+  use crate::media::L1Track;
+
+  #[test]
+  fn track_round_trip() {
+    let track = L1Track {
+      id2: Some(Source::Migu.track("600902000006889366")),
+      lyric_url: Some("https://example.com/a.lrc".to_string()),
+      playable: false,
+      ..L1Track::new(Source::Migu.track("6005751EU86"), "song")
+    };
+    let json = serde_json::to_string(&track).unwrap();
+    let decoded: L1Track = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.id, track.id);
+    assert_eq!(decoded.id2, track.id2);
+    assert_eq!(decoded.source, Source::Migu);
+    assert!(!decoded.playable);
+
+    let legacy = r#"{"id":"kwtrack_1","title":"t","artist":"a","artist_id":null,
+      "album_id":"kwalbum_2","album":"b","source":"kuwo","source_url":"","img_url":"","url":null}"#;
+    let decoded: L1Track = serde_json::from_str(legacy).unwrap();
+    assert_eq!(decoded.album_id, Some(Source::Kuwo.album("2")));
+    assert!(decoded.id2.is_none());
+    assert!(decoded.playable);
+  }
+
+  #[test]
+  fn track_without_album_round_trip() {
+    let item = KugouSongInPlaylist {
+      hash: "ABC123".to_string(),
+      album_id: "".to_string(),
+    };
+    let track = L1Track::from(&item);
+    assert!(track.album_id.is_none());
+
+    let json = serde_json::to_string(&track).unwrap();
+    let decoded: L1Track = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.id, Source::Kugou.track("ABC123"));
+    assert!(decoded.album_id.is_none());
+  }
 }
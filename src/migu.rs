@@ -1,17 +1,39 @@
-use crate::utils::generate_uuid;
+use crate::id::Source;
+use crate::media::{Bitrate, L1SearchResult, L1Track, Provider, ProviderError, SearchQuery};
+use crate::utils::{
+  create_md5, fetch_json, generate_uuid, parse_endpoint, parse_param, require_param,
+  string_or_number,
+};
+use async_trait::async_trait;
 use chrono::Utc;
-use md5::{Digest, Md5};
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use serde_qs;
 use std::collections::HashMap;
-use std::fmt::{format, Display, Formatter};
 use url::Url;
 
+const PROVIDER: &str = "migu";
+
+const SEARCH_HOST: &str = "https://jadeite.migu.cn";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+  pub search: String,
+}
+
+impl Default for Endpoints {
+  fn default() -> Self {
+    Self {
+      search: SEARCH_HOST.to_string(),
+    }
+  }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 enum SearchSwitch {
   Song,
+  #[allow(dead_code)]
   SongList,
 }
 
@@ -31,53 +53,6 @@ pub struct SearchParams {
   sort: u8,
 }
 
-#[derive(Debug, Serialize)]
-struct SongInPlaylist {
-  id: String,
-  id2: String,
-  title: String,
-  artist: String,
-  artist_id: String,
-  album: String,
-  album_id: String,
-  source: String,
-  source_url: String,
-  img_url: String,
-  lyric_url: String,
-  tlyric_url: String,
-  quality: String,
-  url: Option<String>,
-  song_id: String,
-}
-
-#[derive(Debug, Serialize)]
-pub struct SearchResult {
-  total: u32,
-  result: Vec<SongInPlaylist>,
-}
-
-impl Default for SongInPlaylist {
-  fn default() -> Self {
-    Self {
-      id: "".to_string(),
-      id2: "".to_string(),
-      title: "".to_string(),
-      artist: "".to_string(),
-      artist_id: "".to_string(),
-      album: "".to_string(),
-      album_id: "".to_string(),
-      source: "migu".to_string(),
-      source_url: "".to_string(),
-      img_url: "".to_string(),
-      lyric_url: "".to_string(),
-      tlyric_url: "".to_string(),
-      quality: "".to_string(),
-      url: None,
-      song_id: "".to_string(),
-    }
-  }
-}
-
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct AlbumImage {
@@ -91,6 +66,12 @@ struct Artist {
   name: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RateFormat {
+  format_type: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct SearchItem {
@@ -104,10 +85,31 @@ struct SearchItem {
   singer_id: String,
   song_id: String,
   song_name: String,
-  id: String,
   lrc_url: Option<String>,
   trc_url: Option<String>,
   tone_control: String,
+  #[serde(default, deserialize_with = "string_or_number")]
+  duration: Option<String>,
+  #[serde(default)]
+  rate_formats: Vec<RateFormat>,
+}
+
+impl SearchItem {
+  fn bitrates(&self) -> Vec<Bitrate> {
+    let mut bitrates: Vec<Bitrate> = self
+      .rate_formats
+      .iter()
+      .filter_map(|x| match x.format_type.as_str() {
+        "PQ" => Some(Bitrate::K128),
+        "HQ" => Some(Bitrate::K320),
+        "SQ" | "ZQ" => Some(Bitrate::Lossless),
+        _ => None,
+      })
+      .collect();
+    bitrates.sort();
+    bitrates.dedup();
+    bitrates
+  }
 }
 
 #[derive(Debug, Deserialize)]
@@ -121,9 +123,8 @@ struct SearchResultData {
 #[serde(rename_all = "camelCase")]
 struct SearchResponse {
   code: String,
-  result_num: u32,
   info: String,
-  song_result_data: SearchResultData,
+  song_result_data: Option<SearchResultData>,
 }
 
 impl Default for SearchParams {
@@ -142,9 +143,24 @@ impl Default for SearchParams {
   }
 }
 
+impl From<&SearchQuery> for SearchParams {
+  fn from(query: &SearchQuery) -> Self {
+    SearchParams {
+      keyword: query.keyword.to_string(),
+      page: query.pagination.page.into(),
+      page_size: query.pagination.page_size.clamp(1, u8::MAX.into()) as u8,
+      ..Default::default()
+    }
+  }
+}
+
 impl SearchParams {
-  pub fn build_url(&self) -> Url {
-    let mut url = Url::parse("https://jadeite.migu.cn/music_search/v2/search/searchAll").unwrap();
+  pub fn build_url(&self, endpoints: &Endpoints) -> Result<Url, ProviderError> {
+    let mut url = parse_endpoint(
+      PROVIDER,
+      &endpoints.search,
+      "/music_search/v2/search/searchAll",
+    )?;
 
     let switch_kind = match self.search_switch {
       SearchSwitch::Song => r#"{"song":1}"#,
@@ -154,41 +170,48 @@ impl SearchParams {
     let query = format!("{}&searchSwitch={}", query, switch_kind);
     url.set_query(Some(query.as_str()));
 
-    url
+    Ok(url)
   }
 
-  pub fn from_query(query: &HashMap<String, String>) -> Self {
-    let mut params: SearchParams = Default::default();
+  pub fn from_query(query: &HashMap<String, String>) -> Result<Self, ProviderError> {
+    let mut params = SearchParams {
+      keyword: require_param(PROVIDER, query, "keyword")?.to_string(),
+      ..Default::default()
+    };
 
-    for (key, value) in query.iter() {
-      match key.as_str() {
-        "keyword" => {
-          params.keyword = value.to_string();
-        }
-        "page" => {
-          params.page = value.parse().unwrap();
-        }
-        _ => {}
-      }
+    if let Some(page) = query.get("page") {
+      params.page = parse_param(PROVIDER, "page", page)?;
     }
 
-    params
+    Ok(params)
   }
 }
 
 pub struct Migu<'a> {
   pub client: &'a Client,
+  pub endpoints: Endpoints,
 }
 
-fn create_md5(data: impl AsRef<[u8]>) -> String {
-  let mut hasher = Md5::new();
-  hasher.update(data);
-  let hasher_output = hasher.finalize();
+#[async_trait]
+impl Provider for Migu<'_> {
+  fn name(&self) -> &'static str {
+    PROVIDER
+  }
 
-  format!("{:x}", hasher_output)
+  async fn search(&self, query: &SearchQuery) -> Result<L1SearchResult, ProviderError> {
+    self.search_songs(query.into()).await
+  }
 }
 
-impl Migu<'_> {
+impl<'a> Migu<'a> {
+  pub fn new(client: &'a Client) -> Self {
+    Self::with_endpoints(client, Endpoints::default())
+  }
+
+  pub fn with_endpoints(client: &'a Client, endpoints: Endpoints) -> Self {
+    Migu { client, endpoints }
+  }
+
   pub fn create_client() -> Client {
     let mut headers = header::HeaderMap::new();
     // let origin =
@@ -240,102 +263,99 @@ impl Migu<'_> {
     headers
   }
 
-  pub async fn search(&self, params: SearchParams) -> SearchResult {
-    let url = params.build_url();
+  pub async fn search_songs(&self, params: SearchParams) -> Result<L1SearchResult, ProviderError> {
+    let url = params.build_url(&self.endpoints)?;
     let headers = Migu::build_search_headers(&params.keyword);
 
-    let response = self
-      .client
-      .get(url)
-      .headers(headers)
-      .send()
-      .await
-      .unwrap()
-      .json::<SearchResponse>()
-      .await
-      .unwrap();
+    let (url, response) =
+      fetch_json::<SearchResponse>(PROVIDER, self.client.get(url).headers(headers)).await?;
+
+    let data = match response.song_result_data {
+      Some(data) if response.code == "000000" => data,
+      _ => {
+        return Err(ProviderError::Api {
+          provider: PROVIDER,
+          url,
+          code: response.code,
+          message: response.info,
+        })
+      }
+    };
 
-    let result = response
-      .song_result_data
+    let result = data
       .result
       .iter()
       .map(|x: &SearchItem| {
-        let mut song: SongInPlaylist = Default::default();
-        song.id = format!("mgtrack_{}", x.copyright_id);
-        song.id2 = format!("mgtrack_{}", x.song_id);
-        song.title = x.song_name.to_string();
-
-        match x.artists {
-          Some(ref artists) => {
-            song.artist = artists[0].name.to_string();
-            song.artist_id = artists[0].id.to_string();
-          }
-          _ => {
-            song.artist = x.singer.to_string();
-            song.artist_id = x.singer_id.to_string();
-          }
-        }
-
-        if x.album_id != "1" {
-          song.album = x.album.to_string();
-          song.album_id = format!("mgalbum_{}", x.album_id);
-        } else {
-          song.album = "".to_string();
-          song.album_id = "mgalbum_".to_string();
-        }
-
-        song.source_url = format!("https://music.migu.cn/v3/music/song/{}", x.copyright_id);
-        song.img_url = x.album_imgs[0].img.to_string();
-        if let Some(ref lyric_url) = x.lrc_url {
-          song.lyric_url = lyric_url.to_string();
-        }
-        if let Some(ref tlyric_url) = x.trc_url {
-          song.tlyric_url = tlyric_url.to_string();
-        }
-
-        song.quality = x.tone_control.to_string();
+        let (artist, artist_id) = match x.artists.as_ref().and_then(|artists| artists.first()) {
+          Some(artist) => (artist.name.to_string(), Source::Migu.artist_ref(&artist.id)),
+          _ => (x.singer.to_string(), Source::Migu.artist_ref(&x.singer_id)),
+        };
 
-        let result: i8 = x.copyright.parse().unwrap();
-        if result == 0 {
-          song.url = Some("".to_string());
+        let (album, album_id) = if x.album_id != "1" {
+          (x.album.to_string(), Source::Migu.album_ref(&x.album_id))
         } else {
-          song.url = None;
+          ("".to_string(), None)
+        };
+
+        L1Track {
+          id: Source::Migu.track(&x.copyright_id),
+          id2: Source::Migu.track_ref(&x.song_id),
+          title: x.song_name.to_string(),
+          artist,
+          artist_id,
+          album,
+          album_id,
+          source: Source::Migu,
+          source_url: format!("https://music.migu.cn/v3/music/song/{}", x.copyright_id),
+          img_url: x
+            .album_imgs
+            .first()
+            .map(|image| image.img.to_string())
+            .unwrap_or_default(),
+          url: None,
+          lyric_url: x.lrc_url.clone(),
+          tlyric_url: x.trc_url.clone(),
+          quality: Some(x.tone_control.to_string()),
+          playable: x.copyright != "0",
+          duration: x
+            .duration
+            .as_deref()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .map(|secs| secs * 1000),
+          bitrates: x.bitrates(),
+          ..L1Track::new(Source::Migu.track(&x.copyright_id), "")
         }
-
-        song.song_id = x.song_id.to_string();
-
-        song
       })
-      .collect::<Vec<SongInPlaylist>>();
+      .collect::<Vec<L1Track>>();
 
     // println!("migu search with {:#?}", result);
 
-    SearchResult {
-      total: response.song_result_data.total_count.parse().unwrap(),
-      result,
-    }
+    let total = data
+      .total_count
+      .parse()
+      .map_err(|_| ProviderError::parse(PROVIDER, &url, "totalCount is not a number"))?;
+
+    Ok(L1SearchResult { total, result })
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::migu::{Migu, SearchParams};
+  use crate::media::{Bitrate, ProviderError, SearchQuery};
+  use crate::migu::{Endpoints, Migu, SearchParams};
+  use mockito::Matcher;
   use std::collections::HashMap;
 
   #[test]
   fn build_search_url() {
-    let client = Migu::create_client();
-    let migu = Migu { client: &client };
-
     let mut query = HashMap::new();
     query.insert("keyword".to_string(), "song_name".to_string());
     query.insert("page".to_string(), "1".to_string());
 
-    let params = SearchParams::from_query(&query);
+    let params = SearchParams::from_query(&query).unwrap();
 
-    let url = params.build_url();
-
-    let pairs = url.query_pairs();
+    let url = params.build_url(&Endpoints::default()).unwrap();
+    assert_eq!(url.host_str(), Some("jadeite.migu.cn"));
 
     let xy = format!(
       r#"text=song_name&page=1&sid={}&isCorrect=1&isCopyright=1&pageSize=20&feature=1000000000&sort=1&searchSwitch={{%22song%22:1}}"#,
@@ -346,4 +366,107 @@ mod tests {
 
     println!("{:?}", Migu::build_search_headers(&params.keyword));
   }
+
+  #[test]
+  fn invalid_search_endpoint() {
+    let mut query = HashMap::new();
+    query.insert("keyword".to_string(), "song_name".to_string());
+    let params = SearchParams::from_query(&query).unwrap();
+
+    let endpoints = Endpoints {
+      search: "not a url".to_string(),
+    };
+    assert!(matches!(
+      params.build_url(&endpoints),
+      Err(ProviderError::InvalidParameter { .. })
+    ));
+  }
+
+  #[test]
+  fn validate_search_query() {
+    let mut query = HashMap::new();
+    query.insert("page".to_string(), "1".to_string());
+    assert!(matches!(
+      SearchParams::from_query(&query),
+      Err(ProviderError::MissingParameter { name, .. }) if name == "keyword"
+    ));
+
+    query.insert("keyword".to_string(), "song_name".to_string());
+    query.insert("page".to_string(), "first".to_string());
+    assert!(matches!(
+      SearchParams::from_query(&query),
+      Err(ProviderError::InvalidParameter { name, .. }) if name == "page"
+    ));
+  }
+
+  #[tokio::test]
+  async fn search_songs() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+      .mock(
+        "GET",
+        Matcher::Regex("^/music_search/v2/search/searchAll".into()),
+      )
+      .match_query(Matcher::UrlEncoded("text".into(), "晴天".into()))
+      .with_body(include_str!("../fixtures/migu/search_all.json"))
+      .create_async()
+      .await;
+
+    let client = Migu::create_client();
+    let migu = Migu::with_endpoints(
+      &client,
+      Endpoints {
+        search: server.url(),
+      },
+    );
+    let result = migu
+      .search_songs((&SearchQuery::new("晴天")).into())
+      .await
+      .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(result.total, 512);
+    assert_eq!(result.result.len(), 2);
+
+    let track = &result.result[0];
+    assert_eq!(track.id.to_string(), "mgtrack_60054701923");
+    assert_eq!(track.id2.as_ref().unwrap().id, "1138016367");
+    assert_eq!(track.artist, "周杰伦");
+    assert_eq!(track.album_id.as_ref().unwrap().id, "1121438701");
+    assert!(track.img_url.ends_with("a.jpg"));
+    assert_eq!(track.duration, Some(269_000));
+    assert_eq!(
+      track.bitrates,
+      vec![Bitrate::K128, Bitrate::K320, Bitrate::Lossless]
+    );
+    assert!(track.playable);
+
+    let track = &result.result[1];
+    assert_eq!(track.artist, "群星");
+    assert!(track.album_id.is_none());
+    assert!(!track.playable);
+  }
+
+  #[tokio::test]
+  async fn search_api_error() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("GET", Matcher::Any)
+      .with_body(r#"{"code":"100001","info":"参数错误"}"#)
+      .create_async()
+      .await;
+
+    let client = Migu::create_client();
+    let migu = Migu::with_endpoints(
+      &client,
+      Endpoints {
+        search: server.url(),
+      },
+    );
+
+    assert!(matches!(
+      migu.search_songs((&SearchQuery::new("晴天")).into()).await,
+      Err(ProviderError::Api { code, .. }) if code == "100001"
+    ));
+  }
 }
@@ -1,28 +1,92 @@
-use super::media::{L1PlaylistInfo, Provider};
-use super::utils::create_url;
+use super::id::Source;
+use super::media::{
+  Bitrate, L1AlbumDetail, L1AlbumInfo, L1ArtistDetail, L1ArtistInfo, L1CategoryGroup, L1Lyrics,
+  L1PlaylistCategory, L1PlaylistDetail, L1PlaylistInfo, L1SearchResult, L1Stream, L1Track,
+  L1TrackArtist, L1TrackStream, L1TrackUrl, Pagination, PlaylistQuery, Provider, ProviderError,
+  SearchQuery, SearchType, UnavailableReason,
+};
+use super::utils::{
+  create_md5, create_url, endpoint_url, fetch_json, fetch_text, from_timestamp_millis,
+  parse_endpoint_with_params, parse_param, require_param, string_or_number,
+};
 use async_trait::async_trait;
+use chrono::{TimeDelta, Utc};
 use kuchiki::traits::TendrilSink;
 use kuchiki::{parse_html, NodeRef};
 use rand;
 use rand::Rng;
-use reqwest::{cookie, header, Client};
+use reqwest::{header, Client};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
 use std::string::String;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 
-const HOST: &'static str = "https://music.163.com";
-const PLAYLIST_URL: &'static str = "https://music.163.com/discover/playlist";
-const PLAYLIST_DETAIL_URL: &'static str = "https://music.163.com/weapi/v3/playlist/detail";
-const SONG_DETAIL_URL: &'static str = "https://music.163.com/weapi/v3/song/detail";
-const SONG_LYRICS_URL: &'static str = "https://music.163.com/weapi/song/lyric?csrf_token=";
+mod crypto;
+mod session;
 
-const SECRET_CHARS: &'static str = "012345679abcdef";
+pub use session::CookieJar;
+
+const PROVIDER: &str = "netease";
+
+const HOST: &str = "https://music.163.com";
+const PLAYLIST_PATH: &str = "/discover/playlist";
+const PLAYLIST_DETAIL_PATH: &str = "/weapi/v3/playlist/detail";
+const SONG_DETAIL_PATH: &str = "/weapi/v3/song/detail";
+const SONG_LYRICS_PATH: &str = "/weapi/song/lyric?csrf_token=";
+const SEARCH_PATH: &str = "/weapi/cloudsearch/get/web";
+const SONG_URL_PATH: &str = "/api/song/enhance/player/url/v1";
+const CATALOGUE_PATH: &str = "/weapi/playlist/catalogue";
+const TOPLIST_PATH: &str = "/weapi/toplist";
+const PLAYLIST_LIST_PATH: &str = "/weapi/playlist/list";
+const ALBUM_PATH: &str = "/weapi/v1/album";
+const ARTIST_PATH: &str = "/weapi/v1/artist";
+const ARTIST_ALBUMS_PATH: &str = "/weapi/artist/albums";
+const QR_KEY_PATH: &str = "/weapi/login/qrcode/unikey";
+const QR_CHECK_PATH: &str = "/weapi/login/qrcode/client/login";
+const CELLPHONE_LOGIN_PATH: &str = "/weapi/login/cellphone";
+const ACCOUNT_PATH: &str = "/weapi/w/nuser/account/get";
+const USER_PLAYLIST_PATH: &str = "/weapi/user/playlist";
+
+const LINUXAPI_PATH: &str = "/api/linux/forward";
+const LINUXAPI_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/60.0.3112.90 Safari/537.36";
+
+const SONG_DETAIL_CHUNK_SIZE: usize = 500;
+
+const ANTI_BOT_MARKERS: [&str; 4] = ["安全验证", "验证码", "访问异常", "captcha"];
+
+const SECRET_CHARS: &str = "012345679abcdef";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+  pub api: String,
+}
+
+impl Default for Endpoints {
+  fn default() -> Self {
+    Self {
+      api: HOST.to_string(),
+    }
+  }
+}
 
 pub struct Netease<'a> {
   pub client: &'a Client,
+  pub endpoints: Endpoints,
+  /// The jar behind `client`, read for the csrf token weapi calls carry.
+  pub cookies: Option<&'a CookieJar>,
+}
+
+/// How a request body is encrypted. Each transport has its own routes: weapi
+/// paths start with `/weapi/`, eapi and linuxapi ones with `/api/`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crypto {
+  Weapi,
+  Eapi,
+  Linuxapi,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -32,105 +96,866 @@ pub struct NeteaseFormData {
   pub enc_sec_key: String,
 }
 
+impl NeteaseFormData {
+  pub fn weapi(payload: &impl Serialize) -> Self {
+    let text = serde_json::to_string(payload).unwrap();
+    let (params, enc_sec_key) = crypto::weapi(&text, &Netease::create_secret_key(16));
+
+    NeteaseFormData {
+      params,
+      enc_sec_key,
+    }
+  }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
-struct TrackData {
-  id: u64,
+pub struct TrackData {
+  pub id: u64,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-struct PlaylistData {
-  id: u64,
-  coverImgUrl: String,
-  name: String,
-  description: String,
-  trackIds: Vec<TrackData>,
+#[serde(rename_all = "camelCase")]
+pub struct Creator {
+  pub user_id: u64,
+  pub nickname: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistData {
+  pub id: u64,
+  pub cover_img_url: String,
+  pub name: String,
+  pub description: Option<String>,
+  #[serde(default)]
+  pub track_ids: Vec<TrackData>,
+  pub creator: Option<Creator>,
+  pub play_count: Option<u64>,
+  pub track_count: Option<u64>,
+  #[serde(default)]
+  pub tags: Vec<String>,
+  pub create_time: Option<i64>,
+  pub update_time: Option<i64>,
+}
+
+impl From<&PlaylistData> for L1PlaylistInfo {
+  fn from(playlist: &PlaylistData) -> Self {
+    L1PlaylistInfo {
+      cover_img_url: playlist.cover_img_url.to_string(),
+      source_url: format!("https://music.163.com/#/playlist?id={}", playlist.id),
+      description: playlist.description.clone(),
+      creator_name: playlist.creator.as_ref().map(|x| x.nickname.to_string()),
+      creator_id: playlist.creator.as_ref().map(|x| x.user_id.to_string()),
+      play_count: playlist.play_count,
+      track_count: playlist.track_count,
+      tags: playlist.tags.clone(),
+      created_at: playlist.create_time.and_then(from_timestamp_millis),
+      updated_at: playlist.update_time.and_then(from_timestamp_millis),
+      ..L1PlaylistInfo::new(
+        Source::Netease.playlist(playlist.id.to_string()),
+        &playlist.name,
+      )
+    }
+  }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct PlaylistResponse {
-  playlist: PlaylistData,
+  pub code: i64,
+  pub playlist: Option<PlaylistData>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Artist {
-  id: u64,
-  name: String,
+  pub id: u64,
+  pub name: String,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct Album {
-  id: u64,
-  name: String,
-  picUrl: String,
+  pub id: u64,
+  pub name: String,
+  #[serde(default)]
+  pub pic_url: String,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
+pub struct Quality {
+  pub br: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct Song {
-  id: u64,
-  name: String,
-  ar: Vec<Artist>,
-  al: Album,
+  pub id: u64,
+  pub name: String,
+  pub ar: Vec<Artist>,
+  pub al: Album,
+  #[serde(default)]
+  pub dt: Option<u64>,
+  #[serde(default, deserialize_with = "string_or_number")]
+  pub cd: Option<String>,
+  #[serde(default)]
+  pub no: Option<u32>,
+  #[serde(default)]
+  pub publish_time: Option<i64>,
+  #[serde(default)]
+  pub l: Option<Quality>,
+  #[serde(default)]
+  pub m: Option<Quality>,
+  #[serde(default)]
+  pub h: Option<Quality>,
+  #[serde(default)]
+  pub sq: Option<Quality>,
+  #[serde(default)]
+  pub fee: u8,
+}
+
+impl Song {
+  pub fn bitrates(&self) -> Vec<Bitrate> {
+    [
+      (&self.l, Bitrate::K128),
+      (&self.m, Bitrate::K192),
+      (&self.h, Bitrate::K320),
+      (&self.sq, Bitrate::Lossless),
+    ]
+    .into_iter()
+    .filter(|(quality, _)| quality.is_some())
+    .map(|(_, bitrate)| bitrate)
+    .collect()
+  }
+}
+
+impl From<&Song> for L1Track {
+  fn from(song: &Song) -> Self {
+    let artists = song
+      .ar
+      .iter()
+      .map(|artist| L1TrackArtist {
+        id: Source::Netease.artist_ref(artist.id.to_string()),
+        name: artist.name.to_string(),
+      })
+      .collect();
+
+    L1Track {
+      album: song.al.name.to_string(),
+      album_id: Source::Netease.album_ref(song.al.id.to_string()),
+      source_url: format!("https://music.163.com/#/song?id={}", song.id),
+      img_url: song.al.pic_url.to_string(),
+      duration: song.dt,
+      disc_number: song
+        .cd
+        .as_deref()
+        .and_then(|x| x.trim().parse().ok())
+        .filter(|x| *x > 0),
+      track_number: song.no.filter(|x| *x > 0),
+      release_date: song
+        .publish_time
+        .and_then(from_timestamp_millis)
+        .map(|x| x.date_naive()),
+      bitrates: song.bitrates(),
+      // fee 1 is vip only, 4 requires buying the album
+      vip: song.fee == 1 || song.fee == 4,
+      ..L1Track::new(Source::Netease.track(song.id.to_string()), &song.name)
+    }
+    .with_artists(artists)
+  }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct SongResponse {
-  songs: Vec<Song>,
+  pub code: i64,
+  #[serde(default)]
+  pub songs: Vec<Song>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Lyrics {
-  version: u32,
-  lyric: String,
+  pub version: u32,
+  pub lyric: String,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct LyricResponse {
-  lrc: Lyrics,
-  tlyric: Option<Lyrics>,
+  pub code: i64,
+  /// Missing for instrumentals (`nolyric`) and tracks nobody has uploaded
+  /// lyrics for yet (`uncollected`).
+  #[serde(default)]
+  pub lrc: Option<Lyrics>,
+  #[serde(default)]
+  pub tlyric: Option<Lyrics>,
 }
 
-fn build_playlist_url(param: HashMap<String, String>) -> String {
-  let mut items: Vec<(String, String)> = vec![];
-  let order = param.get("order").unwrap().to_string();
-  let offset = param.get("offset").unwrap().parse().unwrap();
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumData {
+  pub id: u64,
+  pub name: String,
+  pub pic_url: String,
+  pub artist: Option<Artist>,
+  pub publish_time: Option<i64>,
+  pub size: Option<u64>,
+  pub description: Option<String>,
+}
 
-  items.push(("order".to_string(), order));
-  items.push(("offset".to_string(), offset));
+impl From<&AlbumData> for L1AlbumInfo {
+  fn from(album: &AlbumData) -> Self {
+    L1AlbumInfo {
+      id: Source::Netease.album(album.id.to_string()),
+      title: album.name.to_string(),
+      artist: album
+        .artist
+        .as_ref()
+        .map(|x| x.name.to_string())
+        .unwrap_or_default(),
+      artist_id: album
+        .artist
+        .as_ref()
+        .and_then(|x| Source::Netease.artist_ref(x.id.to_string())),
+      cover_img_url: album.pic_url.to_string(),
+      source_url: format!("https://music.163.com/#/album?id={}", album.id),
+      description: album.description.clone().filter(|x| !x.is_empty()),
+      release_date: album
+        .publish_time
+        .and_then(from_timestamp_millis)
+        .map(|x| x.date_naive()),
+      track_count: album.size,
+    }
+  }
+}
 
-  if let Some(val) = param.get("category_id") {
-    items.push(("cat".to_string(), val.to_string()));
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtistData {
+  pub id: u64,
+  pub name: String,
+  pub pic_url: Option<String>,
+  pub img1v1_url: Option<String>,
+  pub brief_desc: Option<String>,
+  pub album_size: Option<u64>,
+}
+
+impl From<&ArtistData> for L1ArtistInfo {
+  fn from(artist: &ArtistData) -> Self {
+    L1ArtistInfo {
+      id: Source::Netease.artist(artist.id.to_string()),
+      name: artist.name.to_string(),
+      cover_img_url: artist
+        .pic_url
+        .as_ref()
+        .or(artist.img1v1_url.as_ref())
+        .cloned()
+        .unwrap_or_default(),
+      source_url: format!("https://music.163.com/#/artist?id={}", artist.id),
+      description: artist.brief_desc.clone().filter(|x| !x.is_empty()),
+      album_count: artist.album_size,
+    }
   }
-  let url = Url::parse_with_params(PLAYLIST_URL, &items).unwrap();
+}
 
-  url.to_string()
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CatalogueItem {
+  pub name: String,
+  pub category: u32,
+  #[serde(default)]
+  pub hot: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CatalogueResponse {
+  pub code: i64,
+  #[serde(default)]
+  pub sub: Vec<CatalogueItem>,
+  #[serde(default)]
+  pub categories: HashMap<String, String>,
+}
+
+impl CatalogueResponse {
+  pub fn groups(&self) -> Vec<L1CategoryGroup> {
+    let mut keys: Vec<(u32, &String)> = self
+      .categories
+      .iter()
+      .filter_map(|(key, name)| key.parse().ok().map(|key| (key, name)))
+      .collect();
+    keys.sort();
+
+    keys
+      .into_iter()
+      .map(|(key, name)| L1CategoryGroup {
+        name: name.to_string(),
+        categories: self
+          .sub
+          .iter()
+          .filter(|x| x.category == key)
+          .map(|x| L1PlaylistCategory {
+            id: x.name.to_string(),
+            name: x.name.to_string(),
+            hot: x.hot,
+          })
+          .collect(),
+      })
+      .collect()
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PlaylistListResponse {
+  pub code: i64,
+  #[serde(default)]
+  pub playlists: Vec<PlaylistData>,
+  pub total: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct DiscoverPlaylists {
+  pub playlists: Vec<L1PlaylistInfo>,
+  /// Entries on the discover page that could not be parsed and were left out.
+  pub skipped: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct UserPlaylistResponse {
+  pub code: i64,
+  #[serde(default)]
+  pub more: bool,
+  #[serde(default)]
+  pub playlist: Vec<PlaylistData>,
+}
+
+#[derive(Debug)]
+pub struct UserPlaylists {
+  pub created: Vec<L1PlaylistInfo>,
+  pub subscribed: Vec<L1PlaylistInfo>,
+  pub has_more: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ToplistResponse {
+  pub code: i64,
+  #[serde(default)]
+  pub list: Vec<PlaylistData>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SongUrlData {
+  pub id: u64,
+  pub url: Option<String>,
+  #[serde(default)]
+  pub br: u32,
+  pub size: Option<u64>,
+  #[serde(rename = "type")]
+  pub format: Option<String>,
+  pub expi: Option<i64>,
+  #[serde(default)]
+  pub code: i64,
+  #[serde(default)]
+  pub fee: u8,
+  pub free_trial_info: Option<serde_json::Value>,
+}
+
+impl SongUrlData {
+  fn into_stream(self) -> L1TrackStream {
+    let id = Source::Netease.track(self.id.to_string());
+    let vip = self.fee == 1 || self.fee == 4;
+
+    match self.url {
+      // a trial clip means only the first 30 seconds are playable without vip
+      Some(url) if !url.is_empty() && self.free_trial_info.is_none() => {
+        L1TrackStream::Available(L1Stream {
+          id,
+          url,
+          bitrate: Some(self.br / 1000).filter(|x| *x > 0),
+          size: self.size.filter(|x| *x > 0),
+          format: self.format.map(|x| x.to_lowercase()),
+          expires_at: self
+            .expi
+            .and_then(TimeDelta::try_seconds)
+            .and_then(|x| Utc::now().checked_add_signed(x)),
+        })
+      }
+      _ if vip || self.free_trial_info.is_some() => L1TrackStream::Unavailable {
+        id,
+        reason: UnavailableReason::VipOnly,
+      },
+      _ => L1TrackStream::Unavailable {
+        id,
+        reason: UnavailableReason::NoCopyright,
+      },
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SongUrlResponse {
+  pub code: i64,
+  #[serde(default)]
+  pub data: Vec<SongUrlData>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct AlbumResponse {
+  pub code: i64,
+  pub album: Option<AlbumData>,
+  #[serde(default)]
+  pub songs: Vec<Song>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtistResponse {
+  pub code: i64,
+  pub artist: Option<ArtistData>,
+  #[serde(default)]
+  pub hot_songs: Vec<Song>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtistAlbumsResponse {
+  pub code: i64,
+  pub artist: Option<ArtistData>,
+  #[serde(default)]
+  pub hot_albums: Vec<AlbumData>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+  pub user_id: u64,
+  pub nickname: String,
+  pub avatar_url: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LoginResponse {
+  pub code: i64,
+  #[serde(alias = "message")]
+  pub msg: Option<String>,
+  pub profile: Option<Profile>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct QrKeyResponse {
+  pub code: i64,
+  pub unikey: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrLogin {
+  pub key: String,
+  /// Encode this into the QR code shown to the user.
+  pub url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QrLoginStatus {
+  Waiting,
+  Scanned,
+  Confirmed,
+  Expired,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudSearchResult {
+  #[serde(default)]
+  pub songs: Vec<Song>,
+  #[serde(default)]
+  pub song_count: u64,
+  #[serde(default)]
+  pub playlists: Vec<PlaylistData>,
+  #[serde(default)]
+  pub playlist_count: u64,
+  #[serde(default)]
+  pub albums: Vec<AlbumData>,
+  #[serde(default)]
+  pub album_count: u64,
+  #[serde(default)]
+  pub artists: Vec<ArtistData>,
+  #[serde(default)]
+  pub artist_count: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CloudSearchResponse {
+  pub code: i64,
+  #[serde(default)]
+  pub result: CloudSearchResult,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistParams {
+  pub order: String,
+  pub category: Option<String>,
+  pub offset: u32,
+  pub limit: u32,
+}
+
+impl Default for PlaylistParams {
+  fn default() -> Self {
+    Self {
+      order: "hot".to_string(),
+      category: None,
+      offset: 0,
+      limit: 35,
+    }
+  }
+}
+
+impl From<&PlaylistQuery> for PlaylistParams {
+  fn from(query: &PlaylistQuery) -> Self {
+    Self {
+      category: query.category_id.clone(),
+      offset: query.pagination.offset(),
+      limit: query.pagination.page_size,
+      ..Default::default()
+    }
+  }
+}
+
+impl PlaylistParams {
+  pub fn from_query(query: &HashMap<String, String>) -> Result<Self, ProviderError> {
+    let order = require_param(PROVIDER, query, "order")?;
+    let offset = require_param(PROVIDER, query, "offset")?;
+
+    Ok(Self {
+      order: order.to_string(),
+      category: query.get("category_id").cloned(),
+      offset: parse_param(PROVIDER, "offset", offset)?,
+      ..Default::default()
+    })
+  }
+
+  pub fn build_url(&self, endpoints: &Endpoints) -> Result<Url, ProviderError> {
+    let mut items: Vec<(&str, String)> = vec![
+      ("order", self.order.to_string()),
+      ("offset", self.offset.to_string()),
+      ("limit", self.limit.to_string()),
+    ];
+    if let Some(ref category) = self.category {
+      items.push(("cat", category.to_string()));
+    }
+
+    parse_endpoint_with_params(PROVIDER, &endpoints.api, PLAYLIST_PATH, &items)
+  }
+
+  pub fn build_payload(&self) -> serde_json::Value {
+    json!({
+      "cat": self.category.as_deref().unwrap_or("全部"),
+      "order": self.order,
+      "offset": self.offset,
+      "limit": self.limit,
+      "total": true,
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchParams {
+  pub keyword: String,
+  pub search_type: SearchType,
+  pub offset: u32,
+  pub limit: u32,
+}
+
+impl Default for SearchParams {
+  fn default() -> Self {
+    Self {
+      keyword: "".to_string(),
+      search_type: SearchType::Song,
+      offset: 0,
+      limit: 30,
+    }
+  }
+}
+
+impl From<&SearchQuery> for SearchParams {
+  fn from(query: &SearchQuery) -> Self {
+    Self {
+      keyword: query.keyword.to_string(),
+      offset: query.pagination.offset(),
+      limit: query.pagination.page_size,
+      ..Default::default()
+    }
+  }
+}
+
+impl SearchParams {
+  pub fn from_query(query: &HashMap<String, String>) -> Result<Self, ProviderError> {
+    let mut params = Self {
+      keyword: require_param(PROVIDER, query, "keyword")?.to_string(),
+      ..Default::default()
+    };
+    if let Some(search_type) = query.get("type") {
+      params.search_type = parse_param(PROVIDER, "type", search_type)?;
+    }
+    if let Some(page) = query.get("curpage") {
+      let page: u32 = parse_param(PROVIDER, "curpage", page)?;
+      params.offset = page.saturating_sub(1) * params.limit;
+    }
+
+    Ok(params)
+  }
+
+  pub fn with_type(self, search_type: SearchType) -> Self {
+    Self {
+      search_type,
+      ..self
+    }
+  }
+
+  fn type_code(&self) -> u32 {
+    match self.search_type {
+      SearchType::Song => 1,
+      SearchType::Album => 10,
+      SearchType::Artist => 100,
+      SearchType::Playlist => 1000,
+    }
+  }
+
+  pub fn build_payload(&self) -> serde_json::Value {
+    json!({
+      "s": self.keyword,
+      "type": self.type_code(),
+      "offset": self.offset,
+      "limit": self.limit,
+      "total": true,
+    })
+  }
 }
 
 #[async_trait]
 impl Provider for Netease<'_> {
-  async fn get_playlists(&self, params: HashMap<String, String>) -> Vec<L1PlaylistInfo> {
-    let url = build_playlist_url(params);
-    let resp = self
-      .client
-      .get(url)
-      // .header("Referer", "http://music.163.com/")
-      // .header("Origin", "http://music.163.com/")
-      .send()
-      .await
-      .unwrap()
-      .text()
-      .await
-      .unwrap();
+  fn name(&self) -> &'static str {
+    PROVIDER
+  }
 
-    let document = parse_html().one(resp);
-    let list_element = document.select_first(".m-cvrlst").unwrap();
-    let mut playlists: Vec<L1PlaylistInfo> = Vec::new();
-    for data in list_element.as_node().select("li").unwrap() {
-      let playlist = Netease::create_playlist(&data.as_node());
-      playlists.push(playlist);
+  async fn get_playlists(
+    &self,
+    query: &PlaylistQuery,
+  ) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    Ok(self.fetch_playlists(query.into()).await?.playlists)
+  }
+
+  async fn get_playlist_categories(&self) -> Result<Vec<L1CategoryGroup>, ProviderError> {
+    let response: CatalogueResponse = self.post_weapi(CATALOGUE_PATH, &json!({})).await?;
+    self.check_code(CATALOGUE_PATH, response.code)?;
+
+    Ok(response.groups())
+  }
+
+  async fn get_toplists(&self) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    let response: ToplistResponse = self.post_weapi(TOPLIST_PATH, &json!({})).await?;
+    self.check_code(TOPLIST_PATH, response.code)?;
+
+    Ok(response.list.iter().map(L1PlaylistInfo::from).collect())
+  }
+
+  async fn get_playlist_detail(
+    &self,
+    playlist_id: &str,
+  ) -> Result<L1PlaylistDetail, ProviderError> {
+    let playlist = self.fetch_playlist_detail(playlist_id).await?;
+    let track_ids: Vec<u64> = playlist.track_ids.iter().map(|x| x.id).collect();
+    let tracks = self.get_tracks(&track_ids).await?;
+
+    Ok(L1PlaylistDetail {
+      info: (&playlist).into(),
+      tracks,
+    })
+  }
+
+  async fn get_track_url(&self, track_id: &str) -> Result<L1TrackUrl, ProviderError> {
+    match self.get_track_stream(track_id, Bitrate::K320).await? {
+      L1TrackStream::Available(stream) => Ok(L1TrackUrl {
+        id: stream.id,
+        url: stream.url,
+      }),
+      L1TrackStream::Unavailable { reason, .. } => Err(ProviderError::Api {
+        provider: PROVIDER,
+        url: endpoint_url(&self.endpoints.api, SONG_URL_PATH),
+        code: reason.as_str().to_string(),
+        message: format!("track {} is not playable", track_id),
+      }),
     }
+  }
+
+  async fn get_track_stream(
+    &self,
+    track_id: &str,
+    bitrate: Bitrate,
+  ) -> Result<L1TrackStream, ProviderError> {
+    let song_id: u64 = parse_param(PROVIDER, "track_id", track_id)?;
+    let response = self.get_song_url(song_id, bitrate).await?;
+
+    Ok(
+      response
+        .data
+        .into_iter()
+        .find(|x| x.id == song_id)
+        .map(SongUrlData::into_stream)
+        .unwrap_or_else(|| L1TrackStream::Unavailable {
+          id: Source::Netease.track(track_id),
+          reason: UnavailableReason::NotFound,
+        }),
+    )
+  }
+
+  async fn get_lyrics(&self, track_id: &str) -> Result<L1Lyrics, ProviderError> {
+    let song_id: u64 = parse_param(PROVIDER, "track_id", track_id)?;
+    let response = self.get_song_lyrics(&song_id.to_string()).await?;
 
-    return playlists;
+    Ok(L1Lyrics {
+      lyric: response.lrc.map(|x| x.lyric).unwrap_or_default(),
+      tlyric: response.tlyric.map(|x| x.lyric).filter(|x| !x.is_empty()),
+    })
   }
+
+  async fn get_album(&self, album_id: &str) -> Result<L1AlbumDetail, ProviderError> {
+    let album_id: u64 = parse_param(PROVIDER, "album_id", album_id)?;
+    let path = format!("{}/{}", ALBUM_PATH, album_id);
+    let response: AlbumResponse = self.post_weapi(&path, &json!({})).await?;
+    self.check_code(&path, response.code)?;
+    let album = response
+      .album
+      .as_ref()
+      .ok_or_else(|| self.parse_error(&path, "album not found"))?;
+
+    Ok(L1AlbumDetail {
+      info: album.into(),
+      tracks: response.songs.iter().map(L1Track::from).collect(),
+    })
+  }
+
+  async fn get_artist(&self, artist_id: &str) -> Result<L1ArtistDetail, ProviderError> {
+    let artist_id: u64 = parse_param(PROVIDER, "artist_id", artist_id)?;
+    let path = format!("{}/{}", ARTIST_PATH, artist_id);
+    let response: ArtistResponse = self.post_weapi(&path, &json!({})).await?;
+    self.check_code(&path, response.code)?;
+    let artist = response
+      .artist
+      .as_ref()
+      .ok_or_else(|| self.parse_error(&path, "artist not found"))?;
+
+    Ok(L1ArtistDetail {
+      info: artist.into(),
+      tracks: response.hot_songs.iter().map(L1Track::from).collect(),
+    })
+  }
+
+  async fn get_artist_albums(
+    &self,
+    artist_id: &str,
+    pagination: &Pagination,
+  ) -> Result<L1SearchResult<L1AlbumInfo>, ProviderError> {
+    let artist_id: u64 = parse_param(PROVIDER, "artist_id", artist_id)?;
+    let path = format!("{}/{}", ARTIST_ALBUMS_PATH, artist_id);
+    let payload = json!({
+      "offset": pagination.offset(),
+      "limit": pagination.page_size,
+      "total": true,
+    });
+    let response: ArtistAlbumsResponse = self.post_weapi(&path, &payload).await?;
+    self.check_code(&path, response.code)?;
+
+    Ok(L1SearchResult {
+      total: response
+        .artist
+        .and_then(|x| x.album_size)
+        .unwrap_or(response.hot_albums.len() as u64),
+      result: response.hot_albums.iter().map(L1AlbumInfo::from).collect(),
+    })
+  }
+
+  async fn search(&self, query: &SearchQuery) -> Result<L1SearchResult, ProviderError> {
+    let result = self.cloudsearch(&query.into()).await?;
+
+    Ok(L1SearchResult {
+      total: result.song_count,
+      result: result.songs.iter().map(L1Track::from).collect(),
+    })
+  }
+
+  async fn search_playlists(
+    &self,
+    query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1PlaylistInfo>, ProviderError> {
+    let params = SearchParams::from(query).with_type(SearchType::Playlist);
+    let result = self.cloudsearch(&params).await?;
+
+    Ok(L1SearchResult {
+      total: result.playlist_count,
+      result: result.playlists.iter().map(L1PlaylistInfo::from).collect(),
+    })
+  }
+
+  async fn search_albums(
+    &self,
+    query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1AlbumInfo>, ProviderError> {
+    let params = SearchParams::from(query).with_type(SearchType::Album);
+    let result = self.cloudsearch(&params).await?;
+
+    Ok(L1SearchResult {
+      total: result.album_count,
+      result: result.albums.iter().map(L1AlbumInfo::from).collect(),
+    })
+  }
+
+  async fn search_artists(
+    &self,
+    query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1ArtistInfo>, ProviderError> {
+    let params = SearchParams::from(query).with_type(SearchType::Artist);
+    let result = self.cloudsearch(&params).await?;
+
+    Ok(L1SearchResult {
+      total: result.artist_count,
+      result: result.artists.iter().map(L1ArtistInfo::from).collect(),
+    })
+  }
+}
+
+fn parse_play_count(text: &str) -> Option<u64> {
+  let text = text.trim();
+  let (count, unit) = if let Some(count) = text.strip_suffix('万') {
+    (count, 10_000.0)
+  } else if let Some(count) = text.strip_suffix('亿') {
+    (count, 100_000_000.0)
+  } else {
+    return text.parse().ok();
+  };
+
+  count
+    .trim()
+    .parse::<f64>()
+    .ok()
+    .map(|count| (count * unit) as u64)
+}
+
+/// Accepts a bare user id or a profile link such as
+/// `https://music.163.com/#/user/home?id=32953014`.
+fn parse_user_id(user: &str) -> Result<u64, ProviderError> {
+  let user = user.trim();
+  let invalid = || ProviderError::invalid(PROVIDER, "user", user);
+  if let Ok(id) = user.parse() {
+    return Ok(id);
+  }
+
+  let url = Url::parse(user).map_err(|_| invalid())?;
+  let fragment = url.fragment().and_then(|x| x.split_once('?')).map(|x| x.1);
+  url
+    .query_pairs()
+    .chain(
+      fragment
+        .into_iter()
+        .flat_map(|x| url::form_urlencoded::parse(x.as_bytes())),
+    )
+    .find(|(key, _)| key == "id")
+    .and_then(|(_, value)| value.parse().ok())
+    .ok_or_else(invalid)
 }
 
 fn get_time() -> u64 {
@@ -139,19 +964,125 @@ fn get_time() -> u64 {
     .duration_since(UNIX_EPOCH)
     .expect("Time went backwards");
 
-  let in_ms = since_the_epoch.as_secs() * 1000 + since_the_epoch.subsec_nanos() as u64 / 1_000_000;
-
-  in_ms
+  since_the_epoch.as_secs() * 1000 + since_the_epoch.subsec_nanos() as u64 / 1_000_000
 }
 
-impl Netease<'_> {
+impl<'a> Netease<'a> {
+  pub fn new(client: &'a Client) -> Self {
+    Self::with_endpoints(client, Endpoints::default())
+  }
+
+  pub fn with_endpoints(client: &'a Client, endpoints: Endpoints) -> Self {
+    Netease {
+      client,
+      endpoints,
+      cookies: None,
+    }
+  }
+
+  /// Sends the csrf token from `cookies`, the jar `client` was built with.
+  pub fn with_cookies(self, cookies: &'a CookieJar) -> Self {
+    Netease {
+      cookies: Some(cookies),
+      ..self
+    }
+  }
+
+  /// Scrapes the discover page, falling back to playlist/list when its layout
+  /// is not recognised.
+  pub async fn fetch_playlists(
+    &self,
+    params: PlaylistParams,
+  ) -> Result<DiscoverPlaylists, ProviderError> {
+    let (url, html) = fetch_text(
+      PROVIDER,
+      self.client.get(params.build_url(&self.endpoints)?),
+    )
+    .await?;
+
+    match Netease::scrape_playlists(&html, &url) {
+      Err(ProviderError::Parse { .. }) => Ok(DiscoverPlaylists {
+        playlists: self.fetch_playlist_list(&params).await?,
+        skipped: 0,
+      }),
+      result => result,
+    }
+  }
+
+  pub async fn fetch_playlist_list(
+    &self,
+    params: &PlaylistParams,
+  ) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    let response: PlaylistListResponse = self
+      .post_weapi(PLAYLIST_LIST_PATH, &params.build_payload())
+      .await?;
+    self.check_code(PLAYLIST_LIST_PATH, response.code)?;
+
+    Ok(
+      response
+        .playlists
+        .iter()
+        .map(L1PlaylistInfo::from)
+        .collect(),
+    )
+  }
+
+  fn scrape_playlists(html: &str, page_url: &str) -> Result<DiscoverPlaylists, ProviderError> {
+    if html.trim().is_empty() {
+      return Err(ProviderError::Empty {
+        provider: PROVIDER,
+        url: page_url.to_string(),
+      });
+    }
+
+    let document = parse_html().one(html);
+    let list_element = match document.select_first(".m-cvrlst") {
+      Ok(list_element) => list_element,
+      Err(_) if ANTI_BOT_MARKERS.iter().any(|x| html.contains(x)) => {
+        return Err(ProviderError::Blocked {
+          provider: PROVIDER,
+          url: page_url.to_string(),
+        })
+      }
+      Err(_) => {
+        return Err(ProviderError::parse(
+          PROVIDER,
+          page_url,
+          "playlist container not found",
+        ))
+      }
+    };
+
+    let mut playlists: Vec<L1PlaylistInfo> = Vec::new();
+    let mut skipped = 0;
+    for data in list_element
+      .as_node()
+      .select("li")
+      .map_err(|_| ProviderError::parse(PROVIDER, page_url, "invalid playlist selector"))?
+    {
+      match Netease::create_playlist(data.as_node(), page_url) {
+        Ok(playlist) => playlists.push(playlist),
+        Err(_) => skipped += 1,
+      }
+    }
+    if playlists.is_empty() && skipped > 0 {
+      return Err(ProviderError::parse(
+        PROVIDER,
+        page_url,
+        format!("none of {skipped} playlist entries could be parsed"),
+      ));
+    }
+
+    Ok(DiscoverPlaylists { playlists, skipped })
+  }
+
   fn create_secret_key(size: u8) -> String {
     let mut rng = rand::thread_rng();
     let mut result: Vec<char> = vec![];
     let chars = SECRET_CHARS.chars();
     let range = 0..chars.count();
 
-    for i in 0..size {
+    for _ in 0..size {
       let index = rng.gen_range(range.clone());
       result.push(SECRET_CHARS.chars().nth(index).unwrap())
     }
@@ -159,124 +1090,978 @@ impl Netease<'_> {
     result.iter().collect()
   }
 
-  pub fn create_cookie_jar() -> cookie::Jar {
+  /// An anonymous jar with the device cookies music.163.com expects.
+  pub fn create_cookie_jar() -> CookieJar {
     let uid = Netease::create_secret_key(32);
     let time = get_time();
     let nid = format!("{uid},{timestamp}", uid = uid, timestamp = time);
 
-    let expire_at = (time + 1000 * 60 * 60 * 24 * 365 * 100) / 1000;
-    println!("nid is {:?}", nid);
-
-    let url = HOST.parse::<Url>().unwrap();
-    let uid_cookie = format!(
-      "_ntes_nuid={}; expires={}; Domain={}",
-      uid,
-      expire_at,
-      url.domain().unwrap()
-    );
-    let nid_cookie = format!(
-      "_ntes_nnid={}; expires={}; Domain={}",
-      uid,
-      expire_at,
-      url.domain().unwrap()
-    );
-
-    let jar = cookie::Jar::default();
-    jar.add_cookie_str(&uid_cookie, &url);
-    jar.add_cookie_str(&nid_cookie, &url);
+    let jar = CookieJar::default();
+    jar.insert("_ntes_nuid", &uid);
+    jar.insert("_ntes_nnid", nid);
 
     jar
   }
 
   pub fn create_client() -> Client {
+    Netease::create_client_with_jar(Arc::new(Netease::create_cookie_jar()))
+  }
+
+  /// Builds a client on top of `jar`; keep the `Arc` around to save the
+  /// session after logging in.
+  pub fn create_client_with_jar(jar: Arc<CookieJar>) -> Client {
     let mut headers = header::HeaderMap::new();
     headers.insert("Referer", header::HeaderValue::from_static(HOST));
 
-    let jar = Netease::create_cookie_jar();
     Client::builder()
       .default_headers(headers)
-      .cookie_provider(Arc::new(jar))
+      .cookie_provider(jar)
       .build()
       .unwrap()
   }
 
-  pub async fn get_playlist_detail(&self, payload: NeteaseFormData) -> PlaylistResponse {
-    let response = self
-      .client
-      .post(PLAYLIST_DETAIL_URL)
-      .form(&payload)
-      .send()
-      .await
-      .unwrap()
-      .json::<PlaylistResponse>()
-      .await
-      .unwrap();
+  /// Posts `payload` to `path` encrypted for `crypto`, for endpoints that
+  /// have no dedicated method here.
+  pub async fn request<T: DeserializeOwned>(
+    &self,
+    crypto: Crypto,
+    path: &str,
+    payload: &impl Serialize,
+  ) -> Result<T, ProviderError> {
+    let api = &self.endpoints.api;
+    let request = match crypto {
+      Crypto::Weapi => {
+        let mut payload = serde_json::to_value(payload).unwrap();
+        if let Some(fields) = payload.as_object_mut() {
+          let csrf_token = self.cookies.map(CookieJar::csrf_token);
+          fields.insert("csrf_token".into(), csrf_token.unwrap_or_default().into());
+        }
+        self
+          .client
+          .post(endpoint_url(api, path))
+          .form(&NeteaseFormData::weapi(&payload))
+      }
+      Crypto::Eapi => {
+        let text = serde_json::to_string(payload).unwrap();
+        self
+          .client
+          .post(endpoint_url(api, &path.replacen("/api/", "/eapi/", 1)))
+          .form(&[("params", crypto::eapi(path, &text))])
+      }
+      Crypto::Linuxapi => {
+        let text = serde_json::to_string(&json!({
+          "method": "POST",
+          "url": endpoint_url(HOST, path),
+          "params": payload,
+        }))
+        .unwrap();
+        self
+          .client
+          .post(endpoint_url(api, LINUXAPI_PATH))
+          .header(header::USER_AGENT, LINUXAPI_USER_AGENT)
+          .form(&[("eparams", crypto::linuxapi(&text))])
+      }
+    };
+    let (_, response) = fetch_json(PROVIDER, request).await?;
 
-    return response;
+    Ok(response)
   }
 
-  pub async fn get_song(&self, payload: NeteaseFormData) -> SongResponse {
-    let response = self
-      .client
-      .post(SONG_DETAIL_URL)
-      .form(&payload)
-      .send()
-      .await
-      .unwrap()
-      .json::<SongResponse>()
-      .await
-      .unwrap();
+  async fn post_weapi<T: DeserializeOwned>(
+    &self,
+    path: &str,
+    payload: &impl Serialize,
+  ) -> Result<T, ProviderError> {
+    self.request(Crypto::Weapi, path, payload).await
+  }
+
+  fn check_code(&self, path: &str, code: i64) -> Result<(), ProviderError> {
+    if code == 200 {
+      return Ok(());
+    }
+
+    Err(ProviderError::Api {
+      provider: PROVIDER,
+      url: endpoint_url(&self.endpoints.api, path),
+      code: code.to_string(),
+      message: format!("{} failed", path),
+    })
+  }
+
+  fn check_login(&self, path: &str, response: &LoginResponse) -> Result<Profile, ProviderError> {
+    if response.code != 200 {
+      return Err(ProviderError::Api {
+        provider: PROVIDER,
+        url: endpoint_url(&self.endpoints.api, path),
+        code: response.code.to_string(),
+        message: response
+          .msg
+          .clone()
+          .unwrap_or_else(|| format!("{} failed", path)),
+      });
+    }
 
     response
+      .profile
+      .clone()
+      .ok_or_else(|| self.parse_error(path, "login profile is missing"))
   }
 
-  pub async fn get_song_lyrics(&self, payload: NeteaseFormData) -> LyricResponse {
-    let response = self
-      .client
-      .post(SONG_LYRICS_URL)
-      .form(&payload)
-      .send()
-      .await
-      .unwrap()
-      .json::<LyricResponse>()
-      .await
-      .unwrap();
+  fn parse_error(&self, path: &str, message: &str) -> ProviderError {
+    ProviderError::parse(PROVIDER, &endpoint_url(&self.endpoints.api, path), message)
+  }
+
+  pub async fn create_qr_login(&self) -> Result<QrLogin, ProviderError> {
+    let response: QrKeyResponse = self.post_weapi(QR_KEY_PATH, &json!({ "type": 1 })).await?;
+    self.check_code(QR_KEY_PATH, response.code)?;
+    let key = response
+      .unikey
+      .ok_or_else(|| self.parse_error(QR_KEY_PATH, "qr login key is missing"))?;
+
+    Ok(QrLogin {
+      url: format!("{}/login?codekey={}", HOST, key),
+      key,
+    })
+  }
+
+  /// Polls a QR login; once it is `Confirmed` the client's jar holds `MUSIC_U`.
+  pub async fn check_qr_login(&self, key: &str) -> Result<QrLoginStatus, ProviderError> {
+    let payload = json!({ "key": key, "type": 1 });
+    let response: LoginResponse = self.post_weapi(QR_CHECK_PATH, &payload).await?;
+
+    match response.code {
+      800 => Ok(QrLoginStatus::Expired),
+      801 => Ok(QrLoginStatus::Waiting),
+      802 => Ok(QrLoginStatus::Scanned),
+      803 => Ok(QrLoginStatus::Confirmed),
+      _ => Err(ProviderError::Api {
+        provider: PROVIDER,
+        url: endpoint_url(&self.endpoints.api, QR_CHECK_PATH),
+        code: response.code.to_string(),
+        message: response
+          .msg
+          .unwrap_or_else(|| "qr login failed".to_string()),
+      }),
+    }
+  }
+
+  pub async fn login_cellphone(
+    &self,
+    country_code: &str,
+    phone: &str,
+    password: &str,
+  ) -> Result<Profile, ProviderError> {
+    let payload = json!({
+      "phone": phone,
+      "countrycode": country_code,
+      "password": create_md5(password),
+      "rememberLogin": "true",
+    });
+    let response: LoginResponse = self.post_weapi(CELLPHONE_LOGIN_PATH, &payload).await?;
+
+    self.check_login(CELLPHONE_LOGIN_PATH, &response)
+  }
+
+  /// The logged-in user, or `None` for an anonymous session.
+  pub async fn get_account(&self) -> Result<Option<Profile>, ProviderError> {
+    let response: LoginResponse = self.post_weapi(ACCOUNT_PATH, &json!({})).await?;
+    self.check_code(ACCOUNT_PATH, response.code)?;
+
+    Ok(response.profile)
+  }
+
+  /// One page of the playlists `user` created or subscribed to; `user` is an
+  /// id or a profile URL.
+  pub async fn get_user_playlists(
+    &self,
+    user: &str,
+    pagination: &Pagination,
+  ) -> Result<UserPlaylists, ProviderError> {
+    let user_id = parse_user_id(user)?;
+    let payload = json!({
+      "uid": user_id,
+      "offset": pagination.offset(),
+      "limit": pagination.page_size,
+      "includeVideo": true,
+    });
+    let response: UserPlaylistResponse = self.post_weapi(USER_PLAYLIST_PATH, &payload).await?;
+    self.check_code(USER_PLAYLIST_PATH, response.code)?;
+
+    let (created, subscribed): (Vec<_>, Vec<_>) = response
+      .playlist
+      .iter()
+      .partition(|x| x.creator.as_ref().map(|c| c.user_id) == Some(user_id));
+
+    Ok(UserPlaylists {
+      created: created.into_iter().map(L1PlaylistInfo::from).collect(),
+      subscribed: subscribed.into_iter().map(L1PlaylistInfo::from).collect(),
+      has_more: response.more,
+    })
+  }
+
+  pub async fn fetch_playlist_detail(
+    &self,
+    playlist_id: &str,
+  ) -> Result<PlaylistData, ProviderError> {
+    let payload = json!({
+      "id": playlist_id,
+      "offset": 0,
+      "total": true,
+      "limit": 1000,
+      "n": 1000,
+    });
+
+    let response: PlaylistResponse = self.post_weapi(PLAYLIST_DETAIL_PATH, &payload).await?;
+    self.check_code(PLAYLIST_DETAIL_PATH, response.code)?;
 
     response
+      .playlist
+      .ok_or_else(|| self.parse_error(PLAYLIST_DETAIL_PATH, "playlist missing"))
+  }
+
+  pub async fn get_song(&self, song_ids: &[u64]) -> Result<SongResponse, ProviderError> {
+    let c: Vec<_> = song_ids.iter().map(|id| json!({ "id": id })).collect();
+    let payload = json!({
+      "c": serde_json::to_string(&c).unwrap(),
+      "ids": serde_json::to_string(song_ids).unwrap(),
+    });
+
+    let response: SongResponse = self.post_weapi(SONG_DETAIL_PATH, &payload).await?;
+    self.check_code(SONG_DETAIL_PATH, response.code)?;
+
+    Ok(response)
   }
 
-  fn create_playlist(node_ref: &NodeRef) -> L1PlaylistInfo {
-    let cover_node = node_ref.select_first("img").unwrap();
+  /// Looks up `song_ids` in chunks and returns the tracks in the same order,
+  /// skipping songs that song/detail no longer knows about.
+  pub async fn get_tracks(&self, song_ids: &[u64]) -> Result<Vec<L1Track>, ProviderError> {
+    let tasks = song_ids
+      .chunks(SONG_DETAIL_CHUNK_SIZE)
+      .map(|chunk| self.get_song(chunk));
+    let responses = futures::future::try_join_all(tasks).await?;
+
+    let songs: HashMap<u64, Song> = responses
+      .into_iter()
+      .flat_map(|x| x.songs)
+      .map(|x| (x.id, x))
+      .collect();
+    let tracks = song_ids
+      .iter()
+      .filter_map(|id| songs.get(id))
+      .map(L1Track::from)
+      .collect();
+
+    Ok(tracks)
+  }
+
+  pub async fn get_song_url(
+    &self,
+    song_id: u64,
+    bitrate: Bitrate,
+  ) -> Result<SongUrlResponse, ProviderError> {
+    let level = match bitrate {
+      Bitrate::K128 => "standard",
+      Bitrate::K192 => "higher",
+      Bitrate::K320 => "exhigh",
+      Bitrate::Lossless => "lossless",
+    };
+    let payload = json!({
+      "ids": format!("[{}]", song_id),
+      "level": level,
+      "encodeType": "flac",
+    });
+
+    // the eapi route serves lossless and higher bitrates that weapi caps
+    let response: SongUrlResponse = self.request(Crypto::Eapi, SONG_URL_PATH, &payload).await?;
+    self.check_code(SONG_URL_PATH, response.code)?;
+
+    Ok(response)
+  }
+
+  pub async fn cloudsearch(
+    &self,
+    params: &SearchParams,
+  ) -> Result<CloudSearchResult, ProviderError> {
+    let response: CloudSearchResponse = self
+      .post_weapi(SEARCH_PATH, &params.build_payload())
+      .await?;
+    self.check_code(SEARCH_PATH, response.code)?;
+
+    Ok(response.result)
+  }
+
+  pub async fn get_song_lyrics(&self, song_id: &str) -> Result<LyricResponse, ProviderError> {
+    let payload = json!({
+      "id": song_id,
+      "lv": -1,
+      "tv": -1,
+    });
+    let response: LyricResponse = self.post_weapi(SONG_LYRICS_PATH, &payload).await?;
+    self.check_code(SONG_LYRICS_PATH, response.code)?;
+
+    Ok(response)
+  }
+
+  fn create_playlist(node_ref: &NodeRef, page_url: &str) -> Result<L1PlaylistInfo, ProviderError> {
+    let invalid = |message: &str| ProviderError::parse(PROVIDER, page_url, message);
+
+    let cover_node = node_ref
+      .select_first("img")
+      .map_err(|_| invalid("playlist cover not found"))?;
     let cover_url = cover_node
       .attributes
       .borrow()
       .get("src")
-      .unwrap()
+      .ok_or_else(|| invalid("playlist cover has no src"))?
       .replace("140y140", "512y512");
 
-    let title_container = node_ref.select_first("div").unwrap();
-    let anchor_el = title_container.as_node().select_first("a").unwrap();
+    let title_container = node_ref
+      .select_first("div")
+      .map_err(|_| invalid("playlist title container not found"))?;
+    let anchor_el = title_container
+      .as_node()
+      .select_first("a")
+      .map_err(|_| invalid("playlist link not found"))?;
     let anchor_attrs = anchor_el.attributes.borrow();
-    let title = anchor_attrs.get("title").unwrap().to_string();
-    let href = anchor_attrs.get("href").unwrap();
-    let url = create_url(href).unwrap();
+    let title = anchor_attrs
+      .get("title")
+      .ok_or_else(|| invalid("playlist link has no title"))?
+      .to_string();
+    let href = anchor_attrs
+      .get("href")
+      .ok_or_else(|| invalid("playlist link has no href"))?;
+    let url = create_url(href).map_err(|_| invalid("playlist link is not a valid url"))?;
     let pair = url
       .query_pairs()
-      .find(|(name, value)| name == "id")
-      .unwrap();
+      .find(|(name, _)| name == "id")
+      .ok_or_else(|| invalid("playlist link has no id"))?;
 
-    let mut id = "neplaylist_".to_string();
     let playlist_id = &pair.1.into_owned();
-    id.push_str(playlist_id);
+    let id = Source::Netease.playlist(playlist_id);
     let mut source_url = "https://music.163.com/#/playlist?id=".to_string();
     source_url.push_str(playlist_id);
+
+    let play_count = node_ref
+      .select_first(".nb")
+      .ok()
+      .and_then(|x| parse_play_count(&x.text_contents()));
+    let (creator_name, creator_id) = match node_ref.select_first("a.nm") {
+      Ok(creator_el) => {
+        let creator_attrs = creator_el.attributes.borrow();
+        let creator_id = creator_attrs
+          .get("href")
+          .and_then(|x| create_url(x).ok())
+          .and_then(|x| {
+            x.query_pairs()
+              .find(|(name, _)| name == "id")
+              .map(|(_, value)| value.into_owned())
+          });
+        (
+          Some(creator_el.text_contents().trim().to_string()),
+          creator_id,
+        )
+      }
+      Err(_) => (None, None),
+    };
+
     let playlist = L1PlaylistInfo {
-      id,
       cover_img_url: cover_url,
       source_url,
-      title,
+      creator_name,
+      creator_id,
+      play_count,
+      ..L1PlaylistInfo::new(id, title)
+    };
+
+    Ok(playlist)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::media::{
+    Bitrate, L1Track, L1TrackStream, Pagination, PlaylistQuery, Provider, ProviderError,
+    SearchQuery, SearchType, UnavailableReason,
+  };
+  use crate::netease::{
+    parse_play_count, parse_user_id, CookieJar, Crypto, DiscoverPlaylists, Endpoints, Netease,
+    QrLoginStatus, SearchParams, Song, SongUrlResponse,
+  };
+  use std::collections::HashMap;
+  use std::sync::Arc;
+
+  #[test]
+  fn parse_discover_play_count() {
+    assert_eq!(parse_play_count("9527"), Some(9527));
+    assert_eq!(parse_play_count(" 13万 "), Some(130_000));
+    assert_eq!(parse_play_count("1.5亿"), Some(150_000_000));
+    assert_eq!(parse_play_count("很多"), None);
+  }
+
+  #[test]
+  fn song_detail_to_track() {
+    let json = r#"{"id":186016,"name":"晴天","ar":[{"id":6452,"name":"周杰伦"}],
+      "al":{"id":18905,"name":"叶惠美","picUrl":"https://p1.music.126.net/a.jpg"},
+      "dt":269000,"cd":"01","no":3,"publishTime":1059580800000,"fee":1,
+      "l":{"br":128000},"h":{"br":320000},"m":null}"#;
+    let song: Song = serde_json::from_str(json).unwrap();
+    let track = L1Track::from(&song);
+
+    assert_eq!(track.id.to_string(), "netrack_186016");
+    assert_eq!(track.duration, Some(269000));
+    assert_eq!(track.disc_number, Some(1));
+    assert_eq!(track.track_number, Some(3));
+    assert_eq!(track.release_date.unwrap().to_string(), "2003-07-30");
+    assert_eq!(track.bitrates, vec![Bitrate::K128, Bitrate::K320]);
+    assert!(track.vip);
+  }
+
+  #[tokio::test]
+  async fn fetch_discover_playlists() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+      .mock("GET", mockito::Matcher::Regex("^/discover/playlist".into()))
+      .with_body(include_str!("../fixtures/netease/discover_playlist.html"))
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+    let DiscoverPlaylists { playlists, skipped } = netease
+      .fetch_playlists((&PlaylistQuery::default()).into())
+      .await
+      .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(skipped, 1);
+    assert_eq!(playlists.len(), 2);
+    let first = &playlists[0];
+    assert_eq!(first.id.to_string(), "neplaylist_2829883282");
+    assert_eq!(first.title, "华语私人订制｜最懂你的华语推荐");
+    assert!(first.cover_img_url.ends_with("?param=512y512"));
+    assert_eq!(first.play_count, Some(10_520_000));
+    assert_eq!(first.creator_name.as_deref(), Some("网易云音乐"));
+    assert_eq!(first.creator_id.as_deref(), Some("1463586082"));
+    assert_eq!(playlists[1].play_count, Some(9527));
+  }
+
+  #[tokio::test]
+  async fn post_encrypted_lyric_request() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+      .mock("POST", "/weapi/song/lyric?csrf_token=")
+      .match_header("content-type", "application/x-www-form-urlencoded")
+      .match_body(mockito::Matcher::Regex(
+        "^params=[^&]+&encSecKey=[0-9a-f]{256}$".into(),
+      ))
+      .with_body(r#"{"lrc":{"version":7,"lyric":"[00:00.00]晴天"},"code":200}"#)
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+    let response = netease.get_song_lyrics("186016").await.unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(response.lrc.unwrap().lyric, "[00:00.00]晴天");
+    assert!(response.tlyric.is_none());
+  }
+
+  #[tokio::test]
+  async fn get_playlist_detail() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("POST", "/weapi/v3/playlist/detail")
+      .with_body(include_str!("../fixtures/netease/playlist_detail.json"))
+      .create_async()
+      .await;
+    let songs = server
+      .mock("POST", "/weapi/v3/song/detail")
+      .with_body(include_str!("../fixtures/netease/song_detail.json"))
+      .expect(1)
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+    let detail = netease.get_playlist_detail("2829883282").await.unwrap();
+
+    songs.assert_async().await;
+    assert_eq!(detail.info.id.to_string(), "neplaylist_2829883282");
+    assert_eq!(detail.info.track_count, Some(3));
+    assert_eq!(detail.info.tags, vec!["华语", "流行"]);
+
+    // song/detail answers out of order and drops unknown ids
+    let ids: Vec<String> = detail.tracks.iter().map(|x| x.id.to_string()).collect();
+    assert_eq!(ids, vec!["netrack_186016", "netrack_1901371647"]);
+    let track = &detail.tracks[1];
+    assert_eq!(
+      track.artist_id.as_ref().unwrap().to_string(),
+      "neartist_2116"
+    );
+    assert_eq!(
+      track.album_id.as_ref().unwrap().to_string(),
+      "nealbum_140279207"
+    );
+    assert!(!track.vip);
+  }
+
+  #[tokio::test]
+  async fn get_playlist_detail_error_codes() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("POST", "/weapi/v3/playlist/detail")
+      .with_body(r#"{"code":404,"message":"歌单不存在"}"#)
+      .create_async()
+      .await;
+    server
+      .mock("POST", "/weapi/v3/song/detail")
+      .with_body(r#"{"code":400}"#)
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+
+    match netease.get_playlist_detail("1").await {
+      Err(ProviderError::Api { code, .. }) => assert_eq!(code, "404"),
+      other => panic!("unexpected playlist detail {:?}", other),
+    }
+    match netease.get_tracks(&[186016]).await {
+      Err(ProviderError::Api { code, .. }) => assert_eq!(code, "400"),
+      other => panic!("unexpected tracks {:?}", other),
+    }
+  }
+
+  #[test]
+  fn search_params_from_query() {
+    let mut query = HashMap::new();
+    query.insert("keyword".to_string(), "晴天".to_string());
+    query.insert("type".to_string(), "album".to_string());
+    query.insert("curpage".to_string(), "3".to_string());
+
+    let params = SearchParams::from_query(&query).unwrap();
+    assert_eq!(params.search_type, SearchType::Album);
+    assert_eq!(params.offset, 60);
+    assert_eq!(params.build_payload()["type"], 10);
+
+    query.insert("type".to_string(), "video".to_string());
+    assert!(SearchParams::from_query(&query).is_err());
+  }
+
+  #[tokio::test]
+  async fn search_songs_and_playlists() {
+    let mut server = mockito::Server::new_async().await;
+    let songs = server
+      .mock("POST", "/weapi/cloudsearch/get/web")
+      .with_body(include_str!("../fixtures/netease/cloudsearch_song.json"))
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+    let result = netease.search(&SearchQuery::new("晴天")).await.unwrap();
+
+    songs.assert_async().await;
+    assert_eq!(result.total, 300);
+    assert_eq!(result.result[0].id.to_string(), "netrack_186016");
+    assert_eq!(result.result[1].disc_number, Some(1));
+    assert_eq!(result.result[1].track_number, Some(12));
+
+    songs.remove_async().await;
+    server
+      .mock("POST", "/weapi/cloudsearch/get/web")
+      .with_body(include_str!(
+        "../fixtures/netease/cloudsearch_playlist.json"
+      ))
+      .create_async()
+      .await;
+
+    let result = netease
+      .search_playlists(&SearchQuery::new("周杰伦"))
+      .await
+      .unwrap();
+
+    assert_eq!(result.total, 574);
+    let playlist = &result.result[0];
+    assert_eq!(playlist.id.to_string(), "neplaylist_2079093442");
+    assert_eq!(playlist.creator_name.as_deref(), Some("菠萝不吹雪"));
+    assert_eq!(playlist.track_count, Some(132));
+  }
+
+  #[test]
+  fn song_url_to_stream() {
+    let json = r#"{"code":200,"data":[
+      {"id":1901371647,"url":"http://m701.music.126.net/a.mp3","br":320000,"size":10240365,
+        "type":"MP3","expi":1200,"code":200,"fee":8,"freeTrialInfo":null},
+      {"id":186016,"url":"http://m701.music.126.net/b.mp3","br":128000,"size":960887,
+        "type":"mp3","expi":1200,"code":200,"fee":1,"freeTrialInfo":{"start":0,"end":30}},
+      {"id":404,"url":null,"br":0,"size":0,"type":null,"expi":0,"code":404,"fee":0,"freeTrialInfo":null},
+      {"id":1,"url":"http://m701.music.126.net/c.mp3","br":128000,"size":1,
+        "type":"mp3","expi":9223372036854775807,"code":200,"fee":8,"freeTrialInfo":null}
+    ]}"#;
+    let mut data = serde_json::from_str::<SongUrlResponse>(json).unwrap().data;
+
+    match data.remove(0).into_stream() {
+      L1TrackStream::Available(stream) => {
+        assert_eq!(stream.id.to_string(), "netrack_1901371647");
+        assert_eq!(stream.bitrate, Some(320));
+        assert_eq!(stream.size, Some(10240365));
+        assert_eq!(stream.format.as_deref(), Some("mp3"));
+        assert!(stream.expires_at.is_some());
+      }
+      other => panic!("unexpected {:?}", other),
+    }
+    assert!(matches!(
+      data.remove(0).into_stream(),
+      L1TrackStream::Unavailable {
+        reason: UnavailableReason::VipOnly,
+        ..
+      }
+    ));
+    assert!(matches!(
+      data.remove(0).into_stream(),
+      L1TrackStream::Unavailable {
+        reason: UnavailableReason::NoCopyright,
+        ..
+      }
+    ));
+    match data.remove(0).into_stream() {
+      L1TrackStream::Available(stream) => assert!(stream.expires_at.is_none()),
+      other => panic!("unexpected {:?}", other),
+    }
+  }
+
+  #[tokio::test]
+  async fn get_categories_and_toplists() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("POST", "/weapi/playlist/catalogue")
+      .with_body(include_str!("../fixtures/netease/playlist_catalogue.json"))
+      .create_async()
+      .await;
+    server
+      .mock("POST", "/weapi/toplist")
+      .with_body(include_str!("../fixtures/netease/toplist.json"))
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+
+    let groups = netease.get_playlist_categories().await.unwrap();
+    let names: Vec<&str> = groups.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, vec!["语种", "风格", "场景", "情感", "主题"]);
+    assert_eq!(groups[0].categories.len(), 2);
+    assert_eq!(groups[0].categories[0].id, "华语");
+    assert!(!groups[1].categories[1].hot);
+
+    let toplists = netease.get_toplists().await.unwrap();
+    assert_eq!(toplists.len(), 2);
+    assert_eq!(toplists[0].id.to_string(), "neplaylist_19723756");
+    assert_eq!(toplists[0].title, "飙升榜");
+    assert_eq!(toplists[1].track_count, Some(100));
+  }
+
+  #[tokio::test]
+  async fn get_album_and_artist() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("POST", "/weapi/v1/album/18905")
+      .with_body(include_str!("../fixtures/netease/album.json"))
+      .create_async()
+      .await;
+    server
+      .mock("POST", "/weapi/v1/artist/6452")
+      .with_body(include_str!("../fixtures/netease/artist.json"))
+      .create_async()
+      .await;
+    server
+      .mock("POST", "/weapi/artist/albums/6452")
+      .with_body(include_str!("../fixtures/netease/artist_albums.json"))
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+
+    let album = netease.get_album("18905").await.unwrap();
+    assert_eq!(album.info.id.to_string(), "nealbum_18905");
+    assert_eq!(album.info.artist_id.unwrap().to_string(), "neartist_6452");
+    assert_eq!(album.info.release_date.unwrap().to_string(), "2003-07-30");
+    assert_eq!(album.info.track_count, Some(11));
+    let titles: Vec<&str> = album.tracks.iter().map(|x| x.title.as_str()).collect();
+    assert_eq!(titles, vec!["以父之名", "晴天"]);
+
+    let artist = netease.get_artist("6452").await.unwrap();
+    assert_eq!(artist.info.name, "周杰伦");
+    assert_eq!(artist.info.album_count, Some(116));
+    assert!(artist.info.description.is_some());
+    assert_eq!(artist.tracks.len(), 2);
+
+    let albums = netease
+      .get_artist_albums("6452", &Pagination::new(1, 2))
+      .await
+      .unwrap();
+    assert_eq!(albums.total, 116);
+    assert_eq!(albums.result[0].id.to_string(), "nealbum_153325036");
+    assert_eq!(albums.result[0].description, None);
+
+    for id in ["../v1/user", "6452?x=1", ""] {
+      assert!(matches!(
+        netease.get_artist(id).await,
+        Err(ProviderError::InvalidParameter { .. })
+      ));
+    }
+    assert!(matches!(
+      netease.get_album("18905/extra").await,
+      Err(ProviderError::InvalidParameter { .. })
+    ));
+  }
+
+  #[tokio::test]
+  async fn discover_page_fallbacks() {
+    let mut server = mockito::Server::new_async().await;
+    let page = server
+      .mock("GET", mockito::Matcher::Regex("^/discover/playlist".into()))
+      .with_body(include_str!("../fixtures/netease/discover_blocked.html"))
+      .create_async()
+      .await;
+    let list = server
+      .mock("POST", "/weapi/playlist/list")
+      .with_body(include_str!("../fixtures/netease/playlist_list.json"))
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+    let query = PlaylistQuery::default();
+
+    assert!(matches!(
+      netease.get_playlists(&query).await,
+      Err(ProviderError::Blocked { .. })
+    ));
+
+    page.remove_async().await;
+    let page = server
+      .mock("GET", mockito::Matcher::Regex("^/discover/playlist".into()))
+      .with_body("")
+      .create_async()
+      .await;
+    assert!(matches!(
+      netease.get_playlists(&query).await,
+      Err(ProviderError::Empty { .. })
+    ));
+    list.expect(0).assert_async().await;
+
+    page.remove_async().await;
+    server
+      .mock("GET", mockito::Matcher::Regex("^/discover/playlist".into()))
+      .with_body("<html><body><ul class=\"new-layout\"><li>华语</li></ul></body></html>")
+      .create_async()
+      .await;
+    let playlists = netease.get_playlists(&query).await.unwrap();
+    assert_eq!(playlists.len(), 1);
+    assert_eq!(playlists[0].id.to_string(), "neplaylist_2829883282");
+    assert_eq!(playlists[0].creator_name.as_deref(), Some("网易云音乐"));
+  }
+
+  #[test]
+  fn cookie_jar_scopes_cookies() {
+    use reqwest::cookie::CookieStore;
+    use reqwest::header::HeaderValue;
+    use url::Url;
+
+    let jar = CookieJar::default();
+    let origin = Url::parse("https://music.163.com/weapi/login").unwrap();
+    let headers = [
+      HeaderValue::from_static("MUSIC_U=u1; Domain=.163.com; Path=/"),
+      HeaderValue::from_static("__csrf=c1; Path=/weapi"),
+      HeaderValue::from_static("old=1; Expires=Thu, 01 Jan 1970 00:00:00 GMT"),
+      HeaderValue::from_static("evil=1; Domain=example.com"),
+      HeaderValue::from_static("long=1; Path=/other; Max-Age=9223372036854775807"),
+      HeaderValue::from_static("gone=1; Max-Age=-9223372036854775808"),
+    ];
+    jar.set_cookies(&mut headers.iter(), &origin);
+
+    assert_eq!(jar.csrf_token(), "c1");
+    assert!(jar.get("old").is_none());
+    assert!(jar.get("evil").is_none());
+    assert_eq!(jar.get("long").as_deref(), Some("1"));
+    assert!(jar.get("gone").is_none());
+
+    let cookies = |url: &str| {
+      jar
+        .cookies(&Url::parse(url).unwrap())
+        .map(|x| x.to_str().unwrap().to_string())
     };
+    assert_eq!(
+      cookies("https://music.163.com/weapi/song").as_deref(),
+      Some("MUSIC_U=u1; __csrf=c1")
+    );
+    assert_eq!(
+      cookies("https://interface.music.163.com/api").as_deref(),
+      Some("MUSIC_U=u1")
+    );
+    assert_eq!(cookies("https://example.com/weapi"), None);
+  }
+
+  #[tokio::test]
+  async fn qr_login_persists_session() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("POST", "/weapi/login/qrcode/unikey")
+      .with_body(r#"{"code":200,"unikey":"0a5f6c2e-6d1b-4b6e-9c38-3a4f7c7d2e11"}"#)
+      .create_async()
+      .await;
+    server
+      .mock("POST", "/weapi/login/qrcode/client/login")
+      .with_header("set-cookie", "MUSIC_U=00A1B2C3D4; Max-Age=1296000; Path=/")
+      .with_header("set-cookie", "__csrf=5f1e2d3c; Max-Age=1296010; Path=/")
+      .with_body(r#"{"code":803,"message":"授权登陆成功"}"#)
+      .create_async()
+      .await;
+    server
+      .mock("POST", "/weapi/login/cellphone")
+      .with_body(r#"{"code":502,"msg":"密码错误"}"#)
+      .create_async()
+      .await;
+
+    let jar = Arc::new(Netease::create_cookie_jar());
+    let client = Netease::create_client_with_jar(jar.clone());
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+
+    let login = netease.create_qr_login().await.unwrap();
+    assert_eq!(
+      login.url,
+      "https://music.163.com/login?codekey=0a5f6c2e-6d1b-4b6e-9c38-3a4f7c7d2e11"
+    );
+    assert!(!jar.is_logged_in());
+    assert_eq!(
+      netease.check_qr_login(&login.key).await.unwrap(),
+      QrLoginStatus::Confirmed
+    );
+    assert!(jar.is_logged_in());
+    assert_eq!(jar.csrf_token(), "5f1e2d3c");
+
+    let saved = serde_json::to_string(jar.as_ref()).unwrap();
+    let restored = CookieJar::default();
+    restored.restore(serde_json::from_str(&saved).unwrap());
+    assert_eq!(restored.get("MUSIC_U").as_deref(), Some("00A1B2C3D4"));
+    assert!(restored.get("_ntes_nuid").is_some());
+
+    match netease.login_cellphone("86", "13800000000", "123456").await {
+      Err(ProviderError::Api { code, message, .. }) => {
+        assert_eq!(code, "502");
+        assert_eq!(message, "密码错误");
+      }
+      other => panic!("unexpected login result {:?}", other),
+    }
+  }
+
+  #[tokio::test]
+  async fn get_user_playlists() {
+    assert_eq!(parse_user_id("32953014").unwrap(), 32953014);
+    assert_eq!(
+      parse_user_id("https://music.163.com/#/user/home?id=32953014").unwrap(),
+      32953014
+    );
+    assert_eq!(
+      parse_user_id("https://y.music.163.com/m/user?id=32953014&userid=1").unwrap(),
+      32953014
+    );
+    assert!(parse_user_id("https://music.163.com/#/user/home").is_err());
+    assert!(parse_user_id("阿北").is_err());
+
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+      .mock("POST", "/weapi/user/playlist")
+      .with_body(include_str!("../fixtures/netease/user_playlist.json"))
+      .create_async()
+      .await;
+
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+    let playlists = netease
+      .get_user_playlists(
+        "https://music.163.com/#/user/home?id=32953014",
+        &Pagination::new(1, 3),
+      )
+      .await
+      .unwrap();
+
+    mock.assert_async().await;
+    assert!(playlists.has_more);
+    assert_eq!(playlists.created.len(), 2);
+    assert_eq!(playlists.created[0].id.to_string(), "neplaylist_24381616");
+    assert_eq!(playlists.created[1].track_count, Some(46));
+    assert_eq!(playlists.subscribed.len(), 1);
+    assert_eq!(
+      playlists.subscribed[0].id.to_string(),
+      "neplaylist_2829883282"
+    );
+    assert_eq!(
+      playlists.subscribed[0].creator_name.as_deref(),
+      Some("网易云音乐")
+    );
+  }
+
+  #[tokio::test]
+  async fn eapi_and_linuxapi_requests() {
+    let mut server = mockito::Server::new_async().await;
+    let song_url = server
+      .mock("POST", "/eapi/song/enhance/player/url/v1")
+      .match_body(mockito::Matcher::Regex("^params=[0-9A-F]+$".into()))
+      .with_body(
+        r#"{"code":200,"data":[{"id":186016,"url":"http://m801.music.126.net/a.flac",
+          "br":999000,"size":30912335,"type":"flac","expi":1200,"code":200,"fee":8,
+          "freeTrialInfo":null,"level":"lossless"}]}"#,
+      )
+      .create_async()
+      .await;
+    let forward = server
+      .mock("POST", "/api/linux/forward")
+      .match_header("user-agent", mockito::Matcher::Regex("Linux".into()))
+      .match_body(mockito::Matcher::Regex("^eparams=[0-9A-F]+$".into()))
+      .with_body(r#"{"lrc":{"version":7,"lyric":"[00:00.00]晴天"},"code":200}"#)
+      .create_async()
+      .await;
 
-    playlist
+    let client = Netease::create_client();
+    let netease = Netease::with_endpoints(&client, Endpoints { api: server.url() });
+    match netease
+      .get_track_stream("186016", Bitrate::Lossless)
+      .await
+      .unwrap()
+    {
+      L1TrackStream::Available(stream) => {
+        assert_eq!(stream.bitrate, Some(999));
+        assert_eq!(stream.format.as_deref(), Some("flac"));
+      }
+      other => panic!("unexpected {:?}", other),
+    }
+
+    let lyrics: serde_json::Value = netease
+      .request(
+        Crypto::Linuxapi,
+        "/api/song/lyric",
+        &serde_json::json!({ "id": 186016, "lv": -1 }),
+      )
+      .await
+      .unwrap();
+    assert_eq!(lyrics["lrc"]["lyric"], "[00:00.00]晴天");
+
+    song_url.assert_async().await;
+    forward.assert_async().await;
+
+    server
+      .mock("POST", "/eapi/song/enhance/player/url/v1")
+      .with_body(r#"{"code":-460,"data":[]}"#)
+      .create_async()
+      .await;
+    match netease.get_track_stream("186016", Bitrate::K320).await {
+      Err(ProviderError::Api { code, .. }) => assert_eq!(code, "-460"),
+      other => panic!("unexpected {:?}", other),
+    }
   }
 }
@@ -0,0 +1,112 @@
+use crate::utils::create_md5;
+use aes::cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyInit, KeyIvInit};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use num_bigint::BigUint;
+
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+type Aes128EcbEnc = ecb::Encryptor<aes::Aes128>;
+
+const PRESET_KEY: &[u8] = b"0CoJUm6Qyw8W8jud";
+const IV: &[u8] = b"0102030405060708";
+const EAPI_KEY: &[u8] = b"e82ckenh8dichen8";
+const LINUXAPI_KEY: &[u8] = b"rFgB&h#%2?^eDg:Q";
+const PUBLIC_EXPONENT: &str = "010001";
+const MODULUS: &str = "00e0b509f6259df8642dbc35662901477df22677ec152b5ff68ace615bb7b725152b3ab17a876aea8a5aa76d2e417629ec4ee341f56135fccf695280104e0312ecbda92557c93870114af6c9d05c4f7f0c3685b7a46bee255932575cce10b424d813cfe4875d3e82047b97ddef52741d546b8e289dc6935b3ece0462db0a22b8e7";
+
+fn aes_cbc(text: &[u8], key: &[u8]) -> String {
+  let cipher = Aes128CbcEnc::new_from_slices(key, IV).expect("aes key and iv are 16 bytes");
+  let encrypted = cipher.encrypt_padded_vec_mut::<Pkcs7>(text);
+
+  STANDARD.encode(encrypted)
+}
+
+fn aes_ecb_hex(text: &[u8], key: &[u8]) -> String {
+  let cipher = Aes128EcbEnc::new_from_slice(key).expect("aes key is 16 bytes");
+  let encrypted = cipher.encrypt_padded_vec_mut::<Pkcs7>(text);
+
+  encrypted.iter().map(|x| format!("{:02X}", x)).collect()
+}
+
+fn rsa(text: &[u8]) -> String {
+  let reversed: Vec<u8> = text.iter().rev().copied().collect();
+  let base = BigUint::from_bytes_be(&reversed);
+  let exponent = BigUint::parse_bytes(PUBLIC_EXPONENT.as_bytes(), 16).unwrap();
+  let modulus = BigUint::parse_bytes(MODULUS.as_bytes(), 16).unwrap();
+
+  format!(
+    "{:0>256}",
+    base.modpow(&exponent, &modulus).to_str_radix(16)
+  )
+}
+
+/// Encrypts a weapi payload: AES-CBC with the preset key, again with `secret_key`,
+/// and the secret key itself RSA-encrypted as `encSecKey`.
+pub(crate) fn weapi(text: &str, secret_key: &str) -> (String, String) {
+  let params = aes_cbc(
+    aes_cbc(text.as_bytes(), PRESET_KEY).as_bytes(),
+    secret_key.as_bytes(),
+  );
+  let enc_sec_key = rsa(secret_key.as_bytes());
+
+  (params, enc_sec_key)
+}
+
+/// Encrypts an eapi payload for `path` (the `/api/...` form of the route),
+/// signed with an MD5 digest of both.
+pub(crate) fn eapi(path: &str, text: &str) -> String {
+  let digest = create_md5(format!("nobody{}use{}md5forencrypt", path, text));
+  let data = format!("{}-36cd479b6b5-{}-36cd479b6b5-{}", path, text, digest);
+
+  aes_ecb_hex(data.as_bytes(), EAPI_KEY)
+}
+
+/// Encrypts a linuxapi forward request, `{"method","url","params"}` as JSON.
+pub(crate) fn linuxapi(text: &str) -> String {
+  aes_ecb_hex(text.as_bytes(), LINUXAPI_KEY)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::netease::crypto::{eapi, linuxapi, weapi};
+
+  #[test]
+  fn encrypt_weapi_payload() {
+    let (params, enc_sec_key) = weapi(r#"{"id":"186016","lv":-1}"#, "aaaabbbbccccdddd");
+
+    assert_eq!(
+      params,
+      "nFx7RHF436g9QGSDvzawHPXJCOxLLJYzCGluSMb0E3tX5+l2QDqAJUsao0ZLNoot"
+    );
+    assert_eq!(
+      enc_sec_key,
+      "814e4abf9c1c6a2af74a7ecca8843f3052626c5c054584352e3fd38a519bd659\
+       e687cf1c079e1aac5dd9d491af6b8abf92109862ada93dc7b0ef94a8ee79d557\
+       ff2a20512b87ce507e357861366b8542139c67896748852d4086104a8dfc99a2\
+       e2e0640b46a4357407b72407b2849b323425c6ed45a0222e69d551a2e59e15b7"
+    );
+  }
+
+  #[test]
+  fn encrypt_eapi_and_linuxapi_payloads() {
+    assert_eq!(
+      eapi(
+        "/api/song/enhance/player/url/v1",
+        r#"{"ids":"[186016]","level":"exhigh","encodeType":"flac"}"#
+      ),
+      "FA90B329E9614F79E79598F37DC2EDB487F00D1BC4C9B24CD57E6C318B907356\
+       9338432CD7D98D1A3626E997A2C531218543BC970F66310273D4660DAF94B671\
+       9C2FC721B3DDEEEAF7AD2C922A208A3D3AF14D08BA832D5BE8390E493266BD17\
+       6D25E1071E51EEF3361F325C78CC9F573370C2937D7CF5ED6A2B5301613F7C42\
+       7CD4D717C3F59AAE88BC0B92891A29996AA3B102FBE7296AB0DB9EA5C46AD12B"
+    );
+    assert_eq!(
+      linuxapi(
+        r#"{"method":"POST","params":{"id":"186016"},"url":"https://music.163.com/api/song/lyric"}"#
+      ),
+      "A0D9583F4C5FF68DE851D2893A49DE986BDC9600830840B64DD89587B2E6895C\
+       18FF6637E0BAC934ECAB492ADF3A7811D3B9C88502F739EB83DA4CB641139FAD\
+       6D4D95979324F2102EFC031D86B8E252386E5E699A1D21F5C241F678939ECA41"
+    );
+  }
+}
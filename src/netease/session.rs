@@ -0,0 +1,200 @@
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use reqwest::cookie::CookieStore;
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use url::Url;
+
+const SESSION_COOKIE: &str = "MUSIC_U";
+const CSRF_COOKIE: &str = "__csrf";
+const DEFAULT_DOMAIN: &str = "music.163.com";
+
+/// Cookies for music.163.com that can be saved with serde and loaded back,
+/// so a `MUSIC_U` login session survives restarts.
+///
+/// Cookies are keyed by name alone, which is all Netease needs; a second
+/// cookie with the same name replaces the first whatever its scope.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+  cookies: RwLock<BTreeMap<String, Cookie>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Cookie {
+  value: String,
+  domain: String,
+  #[serde(default)]
+  host_only: bool,
+  path: String,
+  expires: Option<DateTime<Utc>>,
+}
+
+impl Cookie {
+  fn is_expired(&self) -> bool {
+    self.expires.is_some_and(|x| x <= Utc::now())
+  }
+
+  fn matches(&self, url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+      return false;
+    };
+    let domain_match =
+      host == self.domain || (!self.host_only && host.ends_with(&format!(".{}", self.domain)));
+    let path = url.path();
+    let path_match = path == self.path
+      || (path.starts_with(&self.path)
+        && (self.path.ends_with('/') || path[self.path.len()..].starts_with('/')));
+
+    domain_match && path_match && !self.is_expired()
+  }
+}
+
+impl CookieJar {
+  pub fn get(&self, name: &str) -> Option<String> {
+    let cookies = self.cookies.read().unwrap();
+    cookies
+      .get(name)
+      .filter(|x| !x.is_expired())
+      .map(|x| x.value.clone())
+  }
+
+  /// Adds a session cookie for music.163.com and its subdomains.
+  pub fn insert(&self, name: impl Into<String>, value: impl Into<String>) {
+    let cookie = Cookie {
+      value: value.into(),
+      domain: DEFAULT_DOMAIN.to_string(),
+      host_only: false,
+      path: "/".to_string(),
+      expires: None,
+    };
+    self.cookies.write().unwrap().insert(name.into(), cookie);
+  }
+
+  pub fn remove(&self, name: &str) {
+    self.cookies.write().unwrap().remove(name);
+  }
+
+  /// Replaces every cookie with the ones from a previously saved jar.
+  pub fn restore(&self, saved: CookieJar) {
+    *self.cookies.write().unwrap() = saved.cookies.into_inner().unwrap();
+  }
+
+  pub fn is_logged_in(&self) -> bool {
+    self.get(SESSION_COOKIE).is_some()
+  }
+
+  pub fn csrf_token(&self) -> String {
+    self.get(CSRF_COOKIE).unwrap_or_default()
+  }
+
+  fn set_cookie(&self, header: &str, url: &Url) {
+    let mut parts = header.split(';').map(str::trim);
+    let Some((name, value)) = parts.next().and_then(|x| x.split_once('=')) else {
+      return;
+    };
+    let Some(host) = url.host_str() else {
+      return;
+    };
+    let mut cookie = Cookie {
+      value: value.to_string(),
+      domain: host.to_string(),
+      host_only: true,
+      path: default_path(url),
+      expires: None,
+    };
+    let mut max_age = None;
+
+    for (key, value) in parts.filter_map(|x| x.split_once('=')) {
+      let value = value.trim();
+      if key.eq_ignore_ascii_case("domain") {
+        let domain = value.trim_start_matches('.').to_ascii_lowercase();
+        // a server may only scope a cookie to its own domain or a parent of it
+        if host != domain && !host.ends_with(&format!(".{}", domain)) {
+          return;
+        }
+        cookie.domain = domain;
+        cookie.host_only = false;
+      } else if key.eq_ignore_ascii_case("path") && value.starts_with('/') {
+        cookie.path = value.to_string();
+      } else if key.eq_ignore_ascii_case("max-age") {
+        max_age = value.parse::<i64>().ok();
+      } else if key.eq_ignore_ascii_case("expires") {
+        cookie.expires = parse_expires(value);
+      }
+    }
+    // Max-Age wins over Expires when a server sends both; an age too large to
+    // represent is kept without an expiry rather than overflowing
+    if let Some(secs) = max_age {
+      cookie.expires = if secs <= 0 {
+        Some(DateTime::<Utc>::MIN_UTC)
+      } else {
+        TimeDelta::try_seconds(secs).and_then(|x| Utc::now().checked_add_signed(x))
+      };
+    }
+
+    if cookie.is_expired() || value.is_empty() {
+      self.remove(name);
+    } else {
+      self
+        .cookies
+        .write()
+        .unwrap()
+        .insert(name.to_string(), cookie);
+    }
+  }
+}
+
+/// The directory of the request path, used when a cookie names no Path.
+fn default_path(url: &Url) -> String {
+  match url.path().rfind('/') {
+    Some(0) | None => "/".to_string(),
+    Some(index) => url.path()[..index].to_string(),
+  }
+}
+
+fn parse_expires(value: &str) -> Option<DateTime<Utc>> {
+  DateTime::parse_from_rfc2822(value)
+    .map(|x| x.with_timezone(&Utc))
+    .or_else(|_| {
+      NaiveDateTime::parse_from_str(value, "%a, %d-%b-%Y %H:%M:%S GMT").map(|x| x.and_utc())
+    })
+    .ok()
+}
+
+impl CookieStore for CookieJar {
+  fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+    for header in cookie_headers.filter_map(|x| x.to_str().ok()) {
+      self.set_cookie(header, url);
+    }
+  }
+
+  fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+    let cookies = self.cookies.read().unwrap();
+    let header = cookies
+      .iter()
+      .filter(|(_, cookie)| cookie.matches(url))
+      .map(|(name, cookie)| format!("{}={}", name, cookie.value))
+      .collect::<Vec<_>>()
+      .join("; ");
+    if header.is_empty() {
+      return None;
+    }
+
+    HeaderValue::from_str(&header).ok()
+  }
+}
+
+impl Serialize for CookieJar {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.cookies.read().unwrap().serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for CookieJar {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    Ok(CookieJar {
+      cookies: RwLock::new(BTreeMap::deserialize(deserializer)?),
+    })
+  }
+}
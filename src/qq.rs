@@ -1,20 +1,109 @@
 use super::media::L1PlaylistInfo;
-use crate::media::{L1PlaylistDetail, L1Track, Provider};
+use crate::id::Source;
+use crate::media::{
+  Bitrate, L1AlbumDetail, L1AlbumInfo, L1ArtistDetail, L1ArtistInfo, L1Lyrics, L1PlaylistDetail,
+  L1SearchResult, L1Stream, L1Track, L1TrackArtist, L1TrackStream, L1TrackUrl, PlaylistQuery,
+  Provider, ProviderError, SearchQuery, SearchType, UnavailableReason,
+};
+use crate::utils::{
+  endpoint_url, fetch_json, from_timestamp, html_decode, parse_datetime,
+  parse_endpoint_with_params, parse_param, require_param, string_or_number,
+};
 use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::NaiveDate;
 use rand;
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use serde_json::json;
 use std::collections::HashMap;
+use url::Url;
+
+const PROVIDER: &str = "qq";
+
+const C_HOST: &str = "https://c.y.qq.com";
+const I_HOST: &str = "https://i.y.qq.com";
+const U_HOST: &str = "https://u.y.qq.com";
+
+const MUSICU_PATH: &str = "/cgi-bin/musicu.fcg";
+const LYRIC_PATH: &str = "/lyric/fcgi-bin/fcg_query_lyric_new.fcg";
+const ALBUM_PATH: &str = "/v8/fcg-bin/fcg_v8_album_info_cp.fcg";
+const SINGER_PATH: &str = "/v8/fcg-bin/fcg_v8_singer_track_cp.fcg";
+
+const IMAGE_HOST: &str = "https://y.gtimg.cn";
+/// Side in pixels of the covers put on tracks; QQ serves 90, 150, 300, 500 and 800.
+pub const DEFAULT_IMAGE_SIZE: u32 = 300;
+const ARTIST_TRACK_LIMIT: u32 = 50;
+const STREAM_HOST: &str = "https://ws.stream.qqmusic.qq.com/";
+
+/// Stream files by quality: filename prefix, extension and the bitrate served.
+const M500: (&str, &str, Bitrate) = ("M500", ".mp3", Bitrate::K128);
+const M800: (&str, &str, Bitrate) = ("M800", ".mp3", Bitrate::K320);
+const F000: (&str, &str, Bitrate) = ("F000", ".flac", Bitrate::Lossless);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+  pub c: String,
+  pub i: String,
+  pub u: String,
+}
+
+impl Default for Endpoints {
+  fn default() -> Self {
+    Self {
+      c: C_HOST.to_string(),
+      i: I_HOST.to_string(),
+      u: U_HOST.to_string(),
+    }
+  }
+}
 
 pub struct QQ<'a> {
   pub client: &'a Client,
+  pub endpoints: Endpoints,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
+struct Creator {
+  name: String,
+  #[serde(default, deserialize_with = "string_or_number")]
+  qq: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
 struct Item {
   dissid: String,
   imgurl: String,
   dissname: String,
+  introduction: Option<String>,
+  listennum: Option<u64>,
+  createtime: Option<String>,
+  creator: Option<Creator>,
+  song_count: Option<u64>,
+}
+
+impl From<Item> for L1PlaylistInfo {
+  fn from(item: Item) -> Self {
+    L1PlaylistInfo {
+      cover_img_url: item.imgurl,
+      source_url: format!(
+        "https://y.qq.com/n/ryqq/playlist/{dissid}",
+        dissid = item.dissid
+      ),
+      description: item.introduction.as_deref().map(html_decode),
+      creator_name: item.creator.as_ref().map(|x| html_decode(&x.name)),
+      creator_id: item.creator.and_then(|x| x.qq),
+      play_count: item.listennum,
+      track_count: item.song_count,
+      created_at: item.createtime.as_deref().and_then(parse_datetime),
+      ..L1PlaylistInfo::new(
+        Source::QQ.playlist(&item.dissid),
+        html_decode(&item.dissname),
+      )
+    }
+  }
 }
 
 #[derive(Deserialize)]
@@ -33,23 +122,316 @@ struct Singer {
   mid: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct Pay {
+  #[serde(default)]
+  payplay: u8,
+}
+
 #[derive(Debug, Deserialize)]
 struct SongData {
   songmid: String,
-  songid: u32,
+  songid: u64,
   songname: String,
   singer: Vec<Singer>,
   albumname: String,
   albummid: String,
+  #[serde(default)]
+  interval: Option<u64>,
+  #[serde(default, rename = "belongCD")]
+  belong_cd: Option<u32>,
+  #[serde(default, rename = "cdIdx")]
+  cd_idx: Option<u32>,
+  #[serde(default)]
+  pubtime: Option<i64>,
+  #[serde(default)]
+  size128: u64,
+  #[serde(default)]
+  size320: u64,
+  #[serde(default)]
+  sizeape: u64,
+  #[serde(default)]
+  sizeflac: u64,
+  pay: Option<Pay>,
+}
+
+impl SongData {
+  fn bitrates(&self) -> Vec<Bitrate> {
+    let mut bitrates = vec![];
+    if self.size128 > 0 {
+      bitrates.push(Bitrate::K128);
+    }
+    if self.size320 > 0 {
+      bitrates.push(Bitrate::K320);
+    }
+    if self.sizeape > 0 || self.sizeflac > 0 {
+      bitrates.push(Bitrate::Lossless);
+    }
+    bitrates
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchAlbumRef {
+  mid: String,
+  name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SearchFile {
+  #[serde(default)]
+  size_128mp3: u64,
+  #[serde(default)]
+  size_320mp3: u64,
+  #[serde(default)]
+  size_ape: u64,
+  #[serde(default)]
+  size_flac: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchPay {
+  #[serde(default)]
+  pay_play: u8,
+}
+
+/// A song in the musicu layout, which names its fields differently from the
+/// older fcg endpoints.
+#[derive(Debug, Deserialize)]
+struct SearchSong {
+  mid: String,
+  id: u64,
+  name: String,
+  singer: Vec<Singer>,
+  album: SearchAlbumRef,
+  interval: Option<u64>,
+  index_cd: Option<u32>,
+  index_album: Option<u32>,
+  time_public: Option<String>,
+  #[serde(default)]
+  file: SearchFile,
+  pay: Option<SearchPay>,
 }
 
-impl SongData {}
+impl From<SearchSong> for SongData {
+  fn from(song: SearchSong) -> Self {
+    SongData {
+      songmid: song.mid,
+      songid: song.id,
+      songname: song.name,
+      singer: song.singer,
+      albumname: song.album.name,
+      albummid: song.album.mid,
+      interval: song.interval,
+      belong_cd: song.index_cd.map(|x| x + 1),
+      cd_idx: song.index_album,
+      pubtime: song
+        .time_public
+        .as_deref()
+        .and_then(parse_datetime)
+        .map(|x| x.timestamp()),
+      size128: song.file.size_128mp3,
+      size320: song.file.size_320mp3,
+      sizeape: song.file.size_ape,
+      sizeflac: song.file.size_flac,
+      pay: song.pay.map(|x| Pay {
+        payplay: x.pay_play,
+      }),
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchAlbum {
+  #[serde(rename = "albumMID")]
+  album_mid: String,
+  album_name: String,
+  album_pic: Option<String>,
+  public_time: Option<String>,
+  singer_name: Option<String>,
+  #[serde(rename = "singerMID")]
+  singer_mid: Option<String>,
+  #[serde(rename = "song_count")]
+  song_count: Option<u64>,
+}
+
+impl From<SearchAlbum> for L1AlbumInfo {
+  fn from(album: SearchAlbum) -> Self {
+    L1AlbumInfo {
+      source_url: format!("https://y.qq.com/n/ryqq/albumDetail/{}", album.album_mid),
+      id: Source::QQ.album(album.album_mid),
+      title: html_decode(&album.album_name),
+      artist: album
+        .singer_name
+        .as_deref()
+        .map(html_decode)
+        .unwrap_or_default(),
+      artist_id: album.singer_mid.and_then(|x| Source::QQ.artist_ref(x)),
+      cover_img_url: album.album_pic.unwrap_or_default(),
+      description: None,
+      release_date: album
+        .public_time
+        .and_then(|x| NaiveDate::parse_from_str(&x, "%Y-%m-%d").ok()),
+      track_count: album.song_count,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchSinger {
+  #[serde(rename = "singerMID")]
+  singer_mid: String,
+  singer_name: String,
+  singer_pic: Option<String>,
+  album_num: Option<u64>,
+}
+
+impl From<SearchSinger> for L1ArtistInfo {
+  fn from(singer: SearchSinger) -> Self {
+    L1ArtistInfo {
+      source_url: format!("https://y.qq.com/n/ryqq/singer/{}", singer.singer_mid),
+      id: Source::QQ.artist(singer.singer_mid),
+      name: html_decode(&singer.singer_name),
+      cover_img_url: singer.singer_pic.unwrap_or_default(),
+      description: None,
+      album_count: singer.album_num,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchList<T> {
+  #[serde(default = "Vec::new")]
+  list: Vec<T>,
+}
+
+impl<T> Default for SearchList<T> {
+  fn default() -> Self {
+    Self { list: vec![] }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchBody {
+  #[serde(default)]
+  song: SearchList<SearchSong>,
+  #[serde(default)]
+  songlist: SearchList<Item>,
+  #[serde(default)]
+  album: SearchList<SearchAlbum>,
+  #[serde(default)]
+  singer: SearchList<SearchSinger>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchMeta {
+  #[serde(default)]
+  sum: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchData {
+  body: SearchBody,
+  meta: SearchMeta,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlbumData {
+  mid: String,
+  name: String,
+  #[serde(rename = "aDate")]
+  a_date: Option<String>,
+  desc: Option<String>,
+  singername: Option<String>,
+  singermid: Option<String>,
+  total: Option<u64>,
+  #[serde(default)]
+  list: Vec<SongData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlbumResponse {
+  code: i64,
+  data: Option<AlbumData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SingerTrack {
+  music_data: SongData,
+}
+
+#[derive(Debug, Deserialize)]
+struct SingerData {
+  singer_mid: String,
+  singer_name: String,
+  #[serde(default)]
+  list: Vec<SingerTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SingerResponse {
+  code: i64,
+  data: Option<SingerData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LyricResponse {
+  #[serde(default)]
+  retcode: i64,
+  #[serde(default)]
+  lyric: String,
+  #[serde(default)]
+  trans: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MidUrlInfo {
+  filename: String,
+  #[serde(default)]
+  purl: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VkeyData {
+  #[serde(default)]
+  midurlinfo: Vec<MidUrlInfo>,
+  #[serde(default)]
+  sip: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicuRequest<T> {
+  code: i64,
+  data: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicuResponse<T> {
+  code: i64,
+  req: Option<MusicuRequest<T>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+  name: String,
+}
 
 #[derive(Debug, Deserialize)]
 struct CDItem {
   logo: String,
-  desc: String,
   dissname: String,
+  desc: Option<String>,
+  nickname: Option<String>,
+  #[serde(default, deserialize_with = "string_or_number")]
+  uin: Option<String>,
+  #[serde(default)]
+  tags: Vec<Tag>,
+  songnum: Option<u64>,
+  visitnum: Option<u64>,
+  ctime: Option<i64>,
   songlist: Vec<SongData>,
 }
 
@@ -58,133 +440,1049 @@ struct DetailResponse {
   cdlist: Vec<CDItem>,
 }
 
-fn build_playlist_url(params: HashMap<String, String>) -> String {
-  let category_id = params.get("category_id").unwrap().to_string();
-  let offset: u32 = params.get("offset").unwrap().parse().unwrap();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistParams {
+  pub category_id: String,
+  pub offset: u32,
+  pub limit: u32,
+}
 
-  let random_num = rand::random::<f64>();
-  return format!(
-    "https://c.y.qq.com/splcloud/fcgi-bin/fcg_get_diss_by_tag.fcg\
-          ?picmid=1&rnd={random_num}&g_tk=732560869\
-          &loginUin=0&hostUin=0&format=json&inCharset=utf8&outCharset=utf-8\
-          &notice=0&platform=yqq.json&needNewCode=0\
-          &categoryId={category_id}&sortId=5&sin={start_offset}&ein={end_offset}
-        ",
-    random_num = random_num,
-    category_id = category_id,
-    start_offset = offset,
-    end_offset = 29 + offset
-  );
+impl Default for PlaylistParams {
+  fn default() -> Self {
+    Self {
+      category_id: "10000000".to_string(),
+      offset: 0,
+      limit: 30,
+    }
+  }
 }
 
-fn build_playlist_detail_url(list_id: &str) -> String {
-  format!(
-    "https://i.y.qq.com/qzone-music/fcg-bin/fcg_ucc_getcdinfo_byids_cp.fcg?\
-            type=1&json=1&utf8=1&onlysong=0\
-            &nosign=1&disstid={list_id}&g_tk=5381&loginUin=0&hostUin=0\
-            &format=json&inCharset=GB2312&outCharset=utf-8&notice=0\
-            &platform=yqq&needNewCode=0",
-    list_id = list_id
-  )
+impl From<&PlaylistQuery> for PlaylistParams {
+  fn from(query: &PlaylistQuery) -> Self {
+    let mut params = Self {
+      offset: query.pagination.offset(),
+      limit: query.pagination.page_size,
+      ..Default::default()
+    };
+    if let Some(ref category_id) = query.category_id {
+      params.category_id = category_id.to_string();
+    }
+
+    params
+  }
 }
 
-#[async_trait]
-impl Provider for QQ<'_> {
-  async fn get_playlists(&self, params: HashMap<String, String>) -> Vec<L1PlaylistInfo> {
-    let url = build_playlist_url(params);
-    let resp = self
-      .client
-      .get(url)
-      .header("Referer", "https://y.qq.com")
-      .header("Origin", "https://y.qq.com/")
-      .send()
-      .await
-      .unwrap()
-      .json::<PlaylistsResponse>()
-      .await
-      .unwrap();
+impl PlaylistParams {
+  pub fn from_query(query: &HashMap<String, String>) -> Result<Self, ProviderError> {
+    let category_id = require_param(PROVIDER, query, "category_id")?;
+    let offset = require_param(PROVIDER, query, "offset")?;
+
+    Ok(Self {
+      category_id: category_id.to_string(),
+      offset: parse_param(PROVIDER, "offset", offset)?,
+      ..Default::default()
+    })
+  }
 
-    let mut playlists: Vec<L1PlaylistInfo> = Vec::new();
+  pub fn build_url(&self, endpoints: &Endpoints) -> Result<Url, ProviderError> {
+    let random_num = rand::random::<f64>().to_string();
+    let start_offset = self.offset.to_string();
+    let end_offset = (self.offset + self.limit.max(1) - 1).to_string();
 
-    for item in resp.data.list {
-      let playlist = L1PlaylistInfo {
-        id: "qqplaylist_".to_string() + &item.dissid,
-        cover_img_url: item.imgurl,
-        source_url: format!(
-          "https://y.qq.com/n/ryqq/playlist/{dissid}",
-          dissid = item.dissid
-        ),
-        title: item.dissname,
-      };
+    parse_endpoint_with_params(
+      PROVIDER,
+      &endpoints.c,
+      "/splcloud/fcgi-bin/fcg_get_diss_by_tag.fcg",
+      &[
+        ("picmid", "1"),
+        ("rnd", &random_num),
+        ("g_tk", "732560869"),
+        ("loginUin", "0"),
+        ("hostUin", "0"),
+        ("format", "json"),
+        ("inCharset", "utf8"),
+        ("outCharset", "utf-8"),
+        ("notice", "0"),
+        ("platform", "yqq.json"),
+        ("needNewCode", "0"),
+        ("categoryId", &self.category_id),
+        ("sortId", "5"),
+        ("sin", &start_offset),
+        ("ein", &end_offset),
+      ],
+    )
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchParams {
+  pub keyword: String,
+  pub search_type: SearchType,
+  pub page: u32,
+  pub page_size: u32,
+}
 
-      playlists.push(playlist);
+impl Default for SearchParams {
+  fn default() -> Self {
+    Self {
+      keyword: "".to_string(),
+      search_type: SearchType::Song,
+      page: 1,
+      page_size: 20,
     }
+  }
+}
 
-    return playlists;
+impl From<&SearchQuery> for SearchParams {
+  fn from(query: &SearchQuery) -> Self {
+    Self {
+      keyword: query.keyword.to_string(),
+      page: query.pagination.page.max(1),
+      page_size: query.pagination.page_size,
+      ..Default::default()
+    }
   }
 }
 
-impl QQ<'_> {
-  fn convert_to_listen1_song(song_data: &SongData) -> L1Track {
-    let source_url = format!(
-      "https://y.qq.com/#type=song&mid={}&tpl=yqq_song_detail",
-      song_data.songmid
-    );
-    let first_singer = &song_data.singer[0];
-    L1Track {
-      id: format!("qqtrack_{}", song_data.songmid),
-      // id2: format!("qqtrack_{}", songData.songid),
-      // title: htmlDecode(songData.songname),
-      title: song_data.songname.to_string(),
-      // artist: htmlDecode(songData.singer[0].name),
-      artist: first_singer.name.to_string(),
-      artist_id: format!("qqartist_{}", first_singer.mid),
-      // album: htmlDecode(songData.albumname),
-      album: song_data.albumname.to_string(),
-      // album_id: `qqalbum_{songData.albummid}`,
-      album_id: format!("qqalbum_{}", song_data.albummid),
-      // img_url: this.qq_get_image_url(songData.albummid, 'album'),
-      img_url: "".to_string(),
-      source: "qq".to_string(),
-      source_url: source_url,
-      // url: `qqtrack_{songData.songmid}`,
-      // url: !qq.qq_is_playable(song) ? '' : undefined
-      url: Some("".to_string()),
-    }
-  }
-
-  pub async fn get_playlist_detail(&self, playlist_id: &str) -> L1PlaylistDetail {
-    let url = build_playlist_detail_url(playlist_id);
-
-    let resp = self
+impl SearchParams {
+  pub fn from_query(query: &HashMap<String, String>) -> Result<Self, ProviderError> {
+    let mut params = Self {
+      keyword: require_param(PROVIDER, query, "keyword")?.to_string(),
+      ..Default::default()
+    };
+    if let Some(search_type) = query.get("type") {
+      params.search_type = parse_param(PROVIDER, "type", search_type)?;
+    }
+    if let Some(page) = query.get("curpage") {
+      params.page = parse_param(PROVIDER, "curpage", page)?;
+    }
+
+    Ok(params)
+  }
+
+  pub fn with_type(self, search_type: SearchType) -> Self {
+    Self {
+      search_type,
+      ..self
+    }
+  }
+
+  fn type_code(&self) -> u32 {
+    match self.search_type {
+      SearchType::Song => 0,
+      SearchType::Album => 2,
+      SearchType::Playlist => 3,
+      SearchType::Artist => 9,
+    }
+  }
+
+  pub fn build_param(&self) -> serde_json::Value {
+    json!({
+      "query": self.keyword,
+      "search_type": self.type_code(),
+      "page_num": self.page,
+      "num_per_page": self.page_size,
+      "grp": 1,
+    })
+  }
+}
+
+/// Cover of an album at `size`x`size`, or an empty string without an album mid.
+pub fn album_image_url(album_mid: &str, size: u32) -> String {
+  image_url("T002", album_mid, size)
+}
+
+/// Photo of an artist at `size`x`size`, or an empty string without a singer mid.
+pub fn artist_image_url(singer_mid: &str, size: u32) -> String {
+  image_url("T001", singer_mid, size)
+}
+
+fn image_url(kind: &str, mid: &str, size: u32) -> String {
+  if mid.is_empty() {
+    return "".to_string();
+  }
+
+  format!(
+    "{}/music/photo_new/{}R{size}x{size}M000{}.jpg",
+    IMAGE_HOST,
+    kind,
+    mid,
+    size = size
+  )
+}
+
+fn build_playlist_detail_url(endpoints: &Endpoints, list_id: &str) -> Result<Url, ProviderError> {
+  parse_endpoint_with_params(
+    PROVIDER,
+    &endpoints.i,
+    "/qzone-music/fcg-bin/fcg_ucc_getcdinfo_byids_cp.fcg",
+    &[
+      ("type", "1"),
+      ("json", "1"),
+      ("utf8", "1"),
+      ("onlysong", "0"),
+      ("nosign", "1"),
+      ("disstid", list_id),
+      ("g_tk", "5381"),
+      ("loginUin", "0"),
+      ("hostUin", "0"),
+      ("format", "json"),
+      ("inCharset", "GB2312"),
+      ("outCharset", "utf-8"),
+      ("notice", "0"),
+      ("platform", "yqq"),
+      ("needNewCode", "0"),
+    ],
+  )
+}
+
+#[async_trait]
+impl Provider for QQ<'_> {
+  fn name(&self) -> &'static str {
+    PROVIDER
+  }
+
+  async fn get_playlists(
+    &self,
+    query: &PlaylistQuery,
+  ) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    self.fetch_playlists(query.into()).await
+  }
+
+  async fn get_playlist_detail(
+    &self,
+    playlist_id: &str,
+  ) -> Result<L1PlaylistDetail, ProviderError> {
+    let url = build_playlist_detail_url(&self.endpoints, playlist_id)?;
+
+    let request = self
       .client
       .get(url)
       .header("Referer", "http://y.qq.com")
-      .header("Origin", "http://y.qq.com/")
-      .send()
-      .await
-      .unwrap()
-      .json::<DetailResponse>()
-      .await
-      .unwrap();
+      .header("Origin", "http://y.qq.com/");
+    let (url, resp) = fetch_json::<DetailResponse>(PROVIDER, request).await?;
 
-    let first = &resp.cdlist[0];
+    let first = resp
+      .cdlist
+      .first()
+      .ok_or_else(|| ProviderError::parse(PROVIDER, &url, "playlist not found"))?;
     let playlist = L1PlaylistInfo {
       cover_img_url: first.logo.to_string(),
-      title: first.dissname.to_string(),
-      id: format!("qqplaylist_{}", playlist_id),
       source_url: format!("https://y.qq.com/n/ryqq/playlist/{}", playlist_id),
+      description: first.desc.as_deref().map(html_decode),
+      creator_name: first.nickname.as_deref().map(html_decode),
+      creator_id: first.uin.clone(),
+      play_count: first.visitnum,
+      track_count: first.songnum,
+      tags: first.tags.iter().map(|x| html_decode(&x.name)).collect(),
+      created_at: first.ctime.and_then(from_timestamp),
+      ..L1PlaylistInfo::new(
+        Source::QQ.playlist(playlist_id),
+        html_decode(&first.dissname),
+      )
     };
     let tracks = first
       .songlist
       .iter()
-      .map(|x| QQ::convert_to_listen1_song(&x))
+      .map(QQ::convert_to_listen1_song)
       .collect::<Vec<L1Track>>();
     let detail = L1PlaylistDetail {
       info: playlist,
-      tracks: tracks,
+      tracks,
+    };
+
+    Ok(detail)
+  }
+
+  async fn get_lyrics(&self, track_id: &str) -> Result<L1Lyrics, ProviderError> {
+    let request = self
+      .client
+      .get(endpoint_url(&self.endpoints.c, LYRIC_PATH))
+      .query(&[
+        ("songmid", track_id),
+        ("g_tk", "5381"),
+        ("format", "json"),
+        ("inCharset", "utf8"),
+        ("outCharset", "utf-8"),
+        ("nobase64", "0"),
+      ])
+      .header("Referer", "https://y.qq.com/");
+    let (url, response) = fetch_json::<LyricResponse>(PROVIDER, request).await?;
+
+    if response.retcode != 0 {
+      return Err(ProviderError::Api {
+        provider: PROVIDER,
+        url,
+        code: response.retcode.to_string(),
+        message: format!("no lyrics for track {}", track_id),
+      });
+    }
+    let decode = |text: &str| -> Result<String, ProviderError> {
+      let bytes = STANDARD
+        .decode(text)
+        .map_err(|e| ProviderError::parse(PROVIDER, &url, e.to_string()))?;
+      let text = String::from_utf8(bytes)
+        .map_err(|e| ProviderError::parse(PROVIDER, &url, e.to_string()))?;
+
+      Ok(html_decode(&text))
+    };
+
+    Ok(L1Lyrics {
+      lyric: decode(&response.lyric)?,
+      tlyric: Some(decode(&response.trans)?).filter(|x| !x.is_empty()),
+    })
+  }
+
+  async fn get_album(&self, album_id: &str) -> Result<L1AlbumDetail, ProviderError> {
+    let request = self
+      .client
+      .get(endpoint_url(&self.endpoints.i, ALBUM_PATH))
+      .query(&[
+        ("platform", "h5page"),
+        ("albummid", album_id),
+        ("g_tk", "938407465"),
+        ("uin", "0"),
+        ("format", "json"),
+        ("inCharset", "utf-8"),
+        ("outCharset", "utf-8"),
+        ("notice", "0"),
+        ("needNewCode", "1"),
+      ])
+      .header("Referer", "https://y.qq.com/");
+    let (url, response) = fetch_json::<AlbumResponse>(PROVIDER, request).await?;
+    let album = match response.data {
+      Some(album) if response.code == 0 => album,
+      _ => {
+        return Err(ProviderError::Api {
+          provider: PROVIDER,
+          url,
+          code: response.code.to_string(),
+          message: format!("album {} not found", album_id),
+        })
+      }
+    };
+
+    let tracks = album.list.iter().map(QQ::convert_to_listen1_song).collect();
+    let info = L1AlbumInfo {
+      id: Source::QQ.album(&album.mid),
+      cover_img_url: album_image_url(&album.mid, DEFAULT_IMAGE_SIZE),
+      source_url: format!("https://y.qq.com/n/ryqq/albumDetail/{}", album.mid),
+      title: html_decode(&album.name),
+      artist: album
+        .singername
+        .as_deref()
+        .map(html_decode)
+        .unwrap_or_default(),
+      artist_id: album.singermid.and_then(|x| Source::QQ.artist_ref(x)),
+      description: album
+        .desc
+        .filter(|x| !x.is_empty())
+        .map(|x| html_decode(&x)),
+      release_date: album
+        .a_date
+        .and_then(|x| NaiveDate::parse_from_str(&x, "%Y-%m-%d").ok()),
+      track_count: album.total,
+    };
+
+    Ok(L1AlbumDetail { info, tracks })
+  }
+
+  /// The artist's most listened tracks, most popular first.
+  async fn get_artist(&self, artist_id: &str) -> Result<L1ArtistDetail, ProviderError> {
+    let limit = ARTIST_TRACK_LIMIT.to_string();
+    let request = self
+      .client
+      .get(endpoint_url(&self.endpoints.i, SINGER_PATH))
+      .query(&[
+        ("platform", "h5page"),
+        ("order", "listen"),
+        ("begin", "0"),
+        ("num", &limit),
+        ("singermid", artist_id),
+        ("g_tk", "938407465"),
+        ("uin", "0"),
+        ("format", "json"),
+        ("inCharset", "utf-8"),
+        ("outCharset", "utf-8"),
+        ("notice", "0"),
+        ("needNewCode", "0"),
+        ("from", "h5"),
+      ])
+      .header("Referer", "https://y.qq.com/");
+    let (url, response) = fetch_json::<SingerResponse>(PROVIDER, request).await?;
+    let singer = match response.data {
+      Some(singer) if response.code == 0 => singer,
+      _ => {
+        return Err(ProviderError::Api {
+          provider: PROVIDER,
+          url,
+          code: response.code.to_string(),
+          message: format!("artist {} not found", artist_id),
+        })
+      }
+    };
+
+    let tracks = singer
+      .list
+      .iter()
+      .map(|x| QQ::convert_to_listen1_song(&x.music_data))
+      .collect();
+    let info = L1ArtistInfo {
+      id: Source::QQ.artist(&singer.singer_mid),
+      cover_img_url: artist_image_url(&singer.singer_mid, DEFAULT_IMAGE_SIZE),
+      source_url: format!("https://y.qq.com/n/ryqq/singer/{}", singer.singer_mid),
+      name: html_decode(&singer.singer_name),
+      description: None,
+      album_count: None,
+    };
+
+    Ok(L1ArtistDetail { info, tracks })
+  }
+
+  async fn get_track_url(&self, track_id: &str) -> Result<L1TrackUrl, ProviderError> {
+    match self.get_track_stream(track_id, Bitrate::K320).await? {
+      L1TrackStream::Available(stream) => Ok(L1TrackUrl {
+        id: stream.id,
+        url: stream.url,
+      }),
+      L1TrackStream::Unavailable { reason, .. } => Err(ProviderError::Api {
+        provider: PROVIDER,
+        url: endpoint_url(&self.endpoints.u, MUSICU_PATH),
+        code: reason.as_str().to_string(),
+        message: format!("track {} is not playable", track_id),
+      }),
+    }
+  }
+
+  /// Asks for every file up to `bitrate` in one vkey call and returns the best
+  /// one that came back with a `purl`.
+  async fn get_track_stream(
+    &self,
+    track_id: &str,
+    bitrate: Bitrate,
+  ) -> Result<L1TrackStream, ProviderError> {
+    let files = match bitrate {
+      Bitrate::K128 | Bitrate::K192 => vec![M500],
+      Bitrate::K320 => vec![M800, M500],
+      Bitrate::Lossless => vec![F000, M800, M500],
+    };
+    let filenames: Vec<String> = files
+      .iter()
+      .map(|(prefix, ext, _)| format!("{}{}{}{}", prefix, track_id, track_id, ext))
+      .collect();
+    let param = json!({
+      "guid": rand::random::<u32>().to_string(),
+      "songmid": vec![track_id; files.len()],
+      "songtype": vec![0; files.len()],
+      "filename": filenames,
+      "uin": "0",
+      "loginflag": 1,
+      "platform": "20",
+    });
+    let data: VkeyData = self
+      .musicu("vkey.GetVkeyServer", "CgiGetVkey", param)
+      .await?;
+
+    let id = Source::QQ.track(track_id);
+    if data.midurlinfo.is_empty() {
+      return Ok(L1TrackStream::Unavailable {
+        id,
+        reason: UnavailableReason::NotFound,
+      });
+    }
+    let sip = data.sip.first().map_or(STREAM_HOST, String::as_str);
+    let stream = files.iter().zip(&filenames).find_map(|(file, filename)| {
+      data
+        .midurlinfo
+        .iter()
+        .find(|x| &x.filename == filename && !x.purl.is_empty())
+        .map(|info| (file, info))
+    });
+
+    Ok(match stream {
+      Some(((_, ext, bitrate), info)) => L1TrackStream::Available(L1Stream {
+        id,
+        url: format!("{}{}", sip, info.purl),
+        bitrate: bitrate.kbps(),
+        size: None,
+        format: Some(ext.trim_start_matches('.').to_string()),
+        expires_at: None,
+      }),
+      // vkey leaves purl empty for tracks that need a paid account
+      None => L1TrackStream::Unavailable {
+        id,
+        reason: UnavailableReason::VipOnly,
+      },
+    })
+  }
+
+  async fn search(&self, query: &SearchQuery) -> Result<L1SearchResult, ProviderError> {
+    let data = self.search_musicu(&query.into()).await?;
+
+    Ok(L1SearchResult {
+      total: data.meta.sum,
+      result: data
+        .body
+        .song
+        .list
+        .into_iter()
+        .map(|x| QQ::convert_to_listen1_song(&x.into()))
+        .collect(),
+    })
+  }
+
+  async fn search_playlists(
+    &self,
+    query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1PlaylistInfo>, ProviderError> {
+    let params = SearchParams::from(query).with_type(SearchType::Playlist);
+    let data = self.search_musicu(&params).await?;
+
+    Ok(L1SearchResult {
+      total: data.meta.sum,
+      result: data
+        .body
+        .songlist
+        .list
+        .into_iter()
+        .map(Into::into)
+        .collect(),
+    })
+  }
+
+  async fn search_albums(
+    &self,
+    query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1AlbumInfo>, ProviderError> {
+    let params = SearchParams::from(query).with_type(SearchType::Album);
+    let data = self.search_musicu(&params).await?;
+
+    Ok(L1SearchResult {
+      total: data.meta.sum,
+      result: data.body.album.list.into_iter().map(Into::into).collect(),
+    })
+  }
+
+  async fn search_artists(
+    &self,
+    query: &SearchQuery,
+  ) -> Result<L1SearchResult<L1ArtistInfo>, ProviderError> {
+    let params = SearchParams::from(query).with_type(SearchType::Artist);
+    let data = self.search_musicu(&params).await?;
+
+    Ok(L1SearchResult {
+      total: data.meta.sum,
+      result: data.body.singer.list.into_iter().map(Into::into).collect(),
+    })
+  }
+}
+
+impl<'a> QQ<'a> {
+  pub fn new(client: &'a Client) -> Self {
+    Self::with_endpoints(client, Endpoints::default())
+  }
+
+  pub fn with_endpoints(client: &'a Client, endpoints: Endpoints) -> Self {
+    QQ { client, endpoints }
+  }
+
+  pub async fn fetch_playlists(
+    &self,
+    params: PlaylistParams,
+  ) -> Result<Vec<L1PlaylistInfo>, ProviderError> {
+    let url = params.build_url(&self.endpoints)?;
+    let request = self
+      .client
+      .get(url)
+      .header("Referer", "https://y.qq.com")
+      .header("Origin", "https://y.qq.com/");
+    let (_, resp) = fetch_json::<PlaylistsResponse>(PROVIDER, request).await?;
+
+    Ok(
+      resp
+        .data
+        .list
+        .into_iter()
+        .map(L1PlaylistInfo::from)
+        .collect(),
+    )
+  }
+
+  /// Calls one `module`/`method` on the musicu gateway and unwraps its data.
+  async fn musicu<T: DeserializeOwned>(
+    &self,
+    module: &str,
+    method: &str,
+    param: serde_json::Value,
+  ) -> Result<T, ProviderError> {
+    let payload = json!({
+      "comm": { "ct": "19", "cv": "1859", "uin": "0" },
+      "req": { "module": module, "method": method, "param": param },
+    });
+    let request = self
+      .client
+      .post(endpoint_url(&self.endpoints.u, MUSICU_PATH))
+      .header("Referer", "https://y.qq.com")
+      .json(&payload);
+    let (url, response) = fetch_json::<MusicuResponse<T>>(PROVIDER, request).await?;
+
+    match response.req {
+      Some(MusicuRequest {
+        code: 0,
+        data: Some(data),
+      }) if response.code == 0 => Ok(data),
+      req => Err(ProviderError::Api {
+        provider: PROVIDER,
+        url,
+        code: req.map_or(response.code, |x| x.code).to_string(),
+        message: format!("{}.{} failed", module, method),
+      }),
+    }
+  }
+
+  async fn search_musicu(&self, params: &SearchParams) -> Result<SearchData, ProviderError> {
+    self
+      .musicu(
+        "music.search.SearchCgiService",
+        "DoSearchForQQMusicDesktop",
+        params.build_param(),
+      )
+      .await
+  }
+
+  fn convert_to_listen1_song(song_data: &SongData) -> L1Track {
+    let source_url = format!(
+      "https://y.qq.com/#type=song&mid={}&tpl=yqq_song_detail",
+      song_data.songmid
+    );
+    let artists = song_data
+      .singer
+      .iter()
+      .map(|singer| L1TrackArtist {
+        id: Source::QQ.artist_ref(&singer.mid),
+        name: html_decode(&singer.name),
+      })
+      .collect();
+    let track = L1Track {
+      id2: Source::QQ.track_ref(song_data.songid.to_string()),
+      album: html_decode(&song_data.albumname),
+      album_id: Source::QQ.album_ref(&song_data.albummid),
+      img_url: album_image_url(&song_data.albummid, DEFAULT_IMAGE_SIZE),
+      source_url,
+      // url is resolved on demand through get_track_stream
+      duration: song_data.interval.map(|secs| secs * 1000),
+      disc_number: song_data.belong_cd.filter(|x| *x > 0),
+      track_number: song_data.cd_idx.filter(|x| *x > 0),
+      release_date: song_data
+        .pubtime
+        .and_then(from_timestamp)
+        .map(|x| x.date_naive()),
+      bitrates: song_data.bitrates(),
+      vip: song_data.pay.as_ref().is_some_and(|x| x.payplay == 1),
+      ..L1Track::new(
+        Source::QQ.track(&song_data.songmid),
+        html_decode(&song_data.songname),
+      )
     };
 
-    return detail;
+    track.with_artists(artists)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::media::{
+    Bitrate, L1TrackStream, Pagination, PlaylistQuery, Provider, SearchQuery, UnavailableReason,
+  };
+  use crate::qq::{album_image_url, artist_image_url, Endpoints, PlaylistParams, SongData, QQ};
+  use mockito::Matcher;
+  use reqwest::Client;
+
+  fn endpoints(server: &mockito::Server) -> Endpoints {
+    Endpoints {
+      c: server.url(),
+      i: server.url(),
+      u: server.url(),
+    }
+  }
+
+  #[test]
+  fn convert_song_with_entities() {
+    let song: SongData = serde_json::from_str(
+      r#"{"songmid":"001","songid":1,"songname":"Tom &amp; Jerry","albumname":"R&amp;B","albummid":"","singer":[{"name":"A &quot;B&quot;","mid":"002"},{"name":"C","mid":""}]}"#,
+    )
+    .unwrap();
+    let track = QQ::convert_to_listen1_song(&song);
+
+    assert_eq!(track.id.to_string(), "qqtrack_001");
+    assert_eq!(track.title, "Tom & Jerry");
+    assert_eq!(track.album, "R&B");
+    assert!(track.album_id.is_none());
+    assert_eq!(track.artist, "A \"B\" / C");
+    assert_eq!(track.artist_id.unwrap().to_string(), "qqartist_002");
+    assert!(track.artists[1].id.is_none());
+  }
+
+  #[test]
+  fn playlist_category_is_encoded() {
+    let params = PlaylistParams {
+      category_id: "1&sin=0".to_string(),
+      ..Default::default()
+    };
+    let url = params.build_url(&Endpoints::default()).unwrap();
+    let pairs: Vec<_> = url.query_pairs().collect();
+
+    assert!(pairs.contains(&("categoryId".into(), "1&sin=0".into())));
+    assert_eq!(pairs.iter().filter(|(key, _)| key == "sin").count(), 1);
+  }
+
+  #[tokio::test]
+  async fn fetch_playlists() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+      .mock(
+        "GET",
+        Matcher::Regex("^/splcloud/fcgi-bin/fcg_get_diss_by_tag.fcg".into()),
+      )
+      .with_body(include_str!("../fixtures/qq/fcg_get_diss_by_tag.json"))
+      .create_async()
+      .await;
+
+    let client = Client::new();
+    let qq = QQ::with_endpoints(&client, endpoints(&server));
+    let playlists = qq
+      .fetch_playlists((&PlaylistQuery::default()).into())
+      .await
+      .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(playlists.len(), 2);
+    assert_eq!(playlists[0].id.to_string(), "qqplaylist_7256912512");
+    assert_eq!(playlists[0].play_count, Some(2398120));
+    assert_eq!(
+      playlists[0].creator_id.as_deref(),
+      Some("1152921504815287426")
+    );
+    assert_eq!(playlists[1].creator_id.as_deref(), Some("2411370217"));
+    assert_eq!(playlists[1].description.as_deref(), Some("温柔的声音"));
+    assert_eq!(
+      playlists[1].created_at.unwrap().to_string(),
+      "2022-10-19 00:00:00 UTC"
+    );
+  }
+
+  #[tokio::test]
+  async fn get_playlist_detail() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock(
+        "GET",
+        Matcher::Regex("^/qzone-music/fcg-bin/fcg_ucc_getcdinfo_byids_cp.fcg".into()),
+      )
+      .match_query(Matcher::UrlEncoded("disstid".into(), "7256912512".into()))
+      .with_body(include_str!("../fixtures/qq/playlist_detail.json"))
+      .create_async()
+      .await;
+
+    let client = Client::new();
+    let qq = QQ::with_endpoints(&client, endpoints(&server));
+    let detail = qq.get_playlist_detail("7256912512").await.unwrap();
+
+    assert_eq!(detail.info.title, "『欧美』冷门宝藏 | 那些值得单曲循环的歌");
+    assert_eq!(detail.info.tags, vec!["欧美", "治愈"]);
+    assert_eq!(detail.info.track_count, Some(2));
+    assert_eq!(detail.tracks.len(), 2);
+
+    let track = &detail.tracks[0];
+    assert_eq!(track.id.to_string(), "qqtrack_001XcBaY4VVtJU");
+    assert_eq!(track.artist, "Doja Cat / Nicki Minaj");
+    assert_eq!(
+      track.artist_id.as_ref().unwrap().to_string(),
+      "qqartist_000ZVODC4YUbbm"
+    );
+    assert_eq!(track.artists.len(), 2);
+    assert_eq!(track.artists[1].name, "Nicki Minaj");
+    assert_eq!(track.album_id.as_ref().unwrap().id, "003RMaRI1iFoYd");
+    assert_eq!(track.duration, Some(237_000));
+    assert_eq!(track.disc_number, Some(1));
+    assert_eq!(track.track_number, Some(2));
+    assert_eq!(track.release_date.unwrap().to_string(), "2020-05-07");
+    assert_eq!(
+      track.bitrates,
+      vec![Bitrate::K128, Bitrate::K320, Bitrate::Lossless]
+    );
+    assert!(track.vip);
+
+    let track = &detail.tracks[1];
+    assert_eq!(track.artist, "");
+    assert!(track.artist_id.is_none());
+    assert!(track.artists.is_empty());
+    assert_eq!(track.track_number, None);
+    assert!(!track.vip);
+  }
+
+  #[tokio::test]
+  async fn search() {
+    let mut server = mockito::Server::new_async().await;
+    let songs = server
+      .mock("POST", "/cgi-bin/musicu.fcg")
+      .match_body(Matcher::PartialJsonString(
+        r#"{"req":{"method":"DoSearchForQQMusicDesktop",
+          "param":{"query":"晴天","search_type":0,"page_num":2,"num_per_page":2}}}"#
+          .into(),
+      ))
+      .with_body(include_str!("../fixtures/qq/search_song.json"))
+      .create_async()
+      .await;
+    server
+      .mock("POST", "/cgi-bin/musicu.fcg")
+      .match_body(Matcher::Regex(r#""search_type":[239]\b"#.into()))
+      .with_body(include_str!("../fixtures/qq/search_mixed.json"))
+      .create_async()
+      .await;
+
+    let client = Client::new();
+    let qq = QQ::with_endpoints(&client, endpoints(&server));
+    let query = SearchQuery {
+      keyword: "晴天".to_string(),
+      pagination: Pagination::new(2, 2),
+    };
+    let result = qq.search(&query).await.unwrap();
+
+    songs.assert_async().await;
+    assert_eq!(result.total, 600);
+    assert_eq!(result.result.len(), 2);
+    let track = &result.result[0];
+    assert_eq!(track.id.to_string(), "qqtrack_0039MnYb0qxYhV");
+    assert_eq!(track.id2.as_ref().unwrap().id, "97773");
+    assert_eq!(track.artist, "周杰伦");
+    assert_eq!(track.album_id.as_ref().unwrap().id, "000MkMni19ClKG");
+    assert_eq!(track.duration, Some(269_000));
+    assert_eq!(track.track_number, Some(3));
+    assert_eq!(track.release_date.unwrap().to_string(), "2003-07-31");
+    assert_eq!(
+      track.bitrates,
+      vec![Bitrate::K128, Bitrate::K320, Bitrate::Lossless]
+    );
+    assert!(track.vip);
+    assert!(result.result[1].release_date.is_none());
+
+    let playlists = qq.search_playlists(&query).await.unwrap();
+    assert_eq!(playlists.result[0].id.to_string(), "qqplaylist_7256912512");
+    assert_eq!(playlists.result[0].track_count, Some(42));
+    assert_eq!(
+      playlists.result[0].creator_id.as_deref(),
+      Some("1152921504815287426")
+    );
+
+    let albums = qq.search_albums(&query).await.unwrap();
+    assert_eq!(albums.result[0].id.to_string(), "qqalbum_000MkMni19ClKG");
+    assert_eq!(
+      albums.result[0].artist_id.as_ref().unwrap().to_string(),
+      "qqartist_0025NhlN2yWrP4"
+    );
+    assert_eq!(albums.result[0].track_count, Some(11));
+
+    let artists = qq.search_artists(&query).await.unwrap();
+    assert_eq!(artists.result[0].name, "周杰伦");
+    assert_eq!(artists.result[0].album_count, Some(38));
+  }
+
+  #[tokio::test]
+  async fn get_track_stream() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("POST", "/cgi-bin/musicu.fcg")
+      .match_body(Matcher::PartialJsonString(
+        r#"{"req":{"param":{"songmid":["0039MnYb0qxYhV","0039MnYb0qxYhV","0039MnYb0qxYhV"]}}}"#
+          .into(),
+      ))
+      .with_body(
+        r#"{"code":0,"req":{"code":0,"data":{"expiration":80400,
+          "midurlinfo":[
+            {"filename":"F0000039MnYb0qxYhV0039MnYb0qxYhV.flac","purl":"","songmid":"0039MnYb0qxYhV"},
+            {"filename":"M8000039MnYb0qxYhV0039MnYb0qxYhV.mp3",
+              "purl":"M8000039MnYb0qxYhV0039MnYb0qxYhV.mp3?guid=1&vkey=ABC&uin=0&fromtag=66",
+              "songmid":"0039MnYb0qxYhV"},
+            {"filename":"M5000039MnYb0qxYhV0039MnYb0qxYhV.mp3",
+              "purl":"M5000039MnYb0qxYhV0039MnYb0qxYhV.mp3?guid=1&vkey=DEF&uin=0&fromtag=66",
+              "songmid":"0039MnYb0qxYhV"}],
+          "sip":["http://ws.stream.qqmusic.qq.com/","http://isure.stream.qqmusic.qq.com/"]}}}"#,
+      )
+      .create_async()
+      .await;
+    server
+      .mock("POST", "/cgi-bin/musicu.fcg")
+      .match_body(Matcher::PartialJsonString(
+        r#"{"req":{"param":{"songmid":["002Hj1Nd0tnIKa"]}}}"#.into(),
+      ))
+      .with_body(
+        r#"{"code":0,"req":{"code":0,"data":{"midurlinfo":[
+          {"filename":"M500002Hj1Nd0tnIKa002Hj1Nd0tnIKa.mp3","purl":"","songmid":"002Hj1Nd0tnIKa"}],
+          "sip":[]}}}"#,
+      )
+      .create_async()
+      .await;
+
+    let client = Client::new();
+    let qq = QQ::with_endpoints(&client, endpoints(&server));
+    match qq
+      .get_track_stream("0039MnYb0qxYhV", Bitrate::Lossless)
+      .await
+      .unwrap()
+    {
+      L1TrackStream::Available(stream) => {
+        assert_eq!(
+          stream.url,
+          "http://ws.stream.qqmusic.qq.com/M8000039MnYb0qxYhV0039MnYb0qxYhV.mp3\
+           ?guid=1&vkey=ABC&uin=0&fromtag=66"
+        );
+        assert_eq!(stream.bitrate, Some(320));
+        assert_eq!(stream.format.as_deref(), Some("mp3"));
+      }
+      other => panic!("unexpected {:?}", other),
+    }
+
+    assert!(matches!(
+      qq.get_track_stream("002Hj1Nd0tnIKa", Bitrate::K128)
+        .await
+        .unwrap(),
+      L1TrackStream::Unavailable {
+        reason: UnavailableReason::VipOnly,
+        ..
+      }
+    ));
+  }
+
+  #[tokio::test]
+  async fn get_lyrics() {
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock("GET", Matcher::Regex("^/lyric/fcgi-bin/fcg_query_lyric_new.fcg".into()))
+      .match_query(Matcher::UrlEncoded("songmid".into(), "0039MnYb0qxYhV".into()))
+      .with_body(
+        r#"{"retcode":0,"code":0,"subcode":0,
+          "lyric":"W3RpOuaZtOWkqV0KW2FyOuWRqOadsOS8pl0KWzAwOjI5LjUwXeaVheS6i+eahOWwj+m7hOiKsSAmYW1wOyAmIzM0O+S7juWHuueUn+mCo+W5tOWwsemjmOedgCYjMzQ7",
+          "trans":"W3RpOlN1bm55IERheV0KWzAwOjI5LjUwXVRoZSBsaXR0bGUgeWVsbG93IGZsb3dlciYjMzk7cyBzdG9yeQ=="}"#,
+      )
+      .create_async()
+      .await;
+    server
+      .mock(
+        "GET",
+        Matcher::Regex("^/lyric/fcgi-bin/fcg_query_lyric_new.fcg".into()),
+      )
+      .match_query(Matcher::UrlEncoded(
+        "songmid".into(),
+        "002Hj1Nd0tnIKa".into(),
+      ))
+      .with_body(r#"{"retcode":0,"code":0,"subcode":0,"lyric":"W3RpOuaZtOWkqV0=","trans":""}"#)
+      .create_async()
+      .await;
+
+    let client = Client::new();
+    let qq = QQ::with_endpoints(&client, endpoints(&server));
+    let lyrics = qq.get_lyrics("0039MnYb0qxYhV").await.unwrap();
+    assert_eq!(
+      lyrics.lyric,
+      "[ti:晴天]\n[ar:周杰伦]\n[00:29.50]故事的小黄花 & \"从出生那年就飘着\""
+    );
+    assert_eq!(
+      lyrics.tlyric.as_deref(),
+      Some("[ti:Sunny Day]\n[00:29.50]The little yellow flower's story")
+    );
+
+    let lyrics = qq.get_lyrics("002Hj1Nd0tnIKa").await.unwrap();
+    assert_eq!(lyrics.lyric, "[ti:晴天]");
+    assert!(lyrics.tlyric.is_none());
+
+    // ids are sent as one query value rather than spliced into the query string
+    let encoded = server
+      .mock(
+        "GET",
+        Matcher::Regex("^/lyric/fcgi-bin/fcg_query_lyric_new.fcg".into()),
+      )
+      .match_query(Matcher::AllOf(vec![
+        Matcher::UrlEncoded("songmid".into(), "x&format=xml".into()),
+        Matcher::UrlEncoded("format".into(), "json".into()),
+      ]))
+      .with_body(r#"{"retcode":0,"code":0,"subcode":0,"lyric":"","trans":""}"#)
+      .create_async()
+      .await;
+    qq.get_lyrics("x&format=xml").await.unwrap();
+    encoded.assert_async().await;
+  }
+
+  #[tokio::test]
+  async fn get_album_and_artist() {
+    assert_eq!(
+      album_image_url("000MkMni19ClKG", 500),
+      "https://y.gtimg.cn/music/photo_new/T002R500x500M000000MkMni19ClKG.jpg"
+    );
+    assert_eq!(
+      artist_image_url("0025NhlN2yWrP4", 150),
+      "https://y.gtimg.cn/music/photo_new/T001R150x150M0000025NhlN2yWrP4.jpg"
+    );
+    assert_eq!(album_image_url("", 300), "");
+
+    let mut server = mockito::Server::new_async().await;
+    server
+      .mock(
+        "GET",
+        Matcher::Regex("^/v8/fcg-bin/fcg_v8_album_info_cp.fcg".into()),
+      )
+      .match_query(Matcher::UrlEncoded(
+        "albummid".into(),
+        "000MkMni19ClKG".into(),
+      ))
+      .with_body(include_str!("../fixtures/qq/album_info.json"))
+      .create_async()
+      .await;
+    server
+      .mock(
+        "GET",
+        Matcher::Regex("^/v8/fcg-bin/fcg_v8_singer_track_cp.fcg".into()),
+      )
+      .match_query(Matcher::UrlEncoded(
+        "singermid".into(),
+        "0025NhlN2yWrP4".into(),
+      ))
+      .with_body(include_str!("../fixtures/qq/singer_track.json"))
+      .create_async()
+      .await;
+
+    let client = Client::new();
+    let qq = QQ::with_endpoints(&client, endpoints(&server));
+    let album = qq.get_album("000MkMni19ClKG").await.unwrap();
+    assert_eq!(album.info.title, "叶惠美");
+    assert_eq!(
+      album.info.artist_id.as_ref().unwrap().to_string(),
+      "qqartist_0025NhlN2yWrP4"
+    );
+    assert_eq!(album.info.release_date.unwrap().to_string(), "2003-07-31");
+    assert_eq!(album.info.track_count, Some(11));
+    assert_eq!(
+      album.info.description.as_deref(),
+      Some("《叶惠美》是周杰伦的第四张个人专辑\n收录了《晴天》《以父之名》等歌曲。")
+    );
+    assert_eq!(
+      album.info.cover_img_url,
+      "https://y.gtimg.cn/music/photo_new/T002R300x300M000000MkMni19ClKG.jpg"
+    );
+    let titles: Vec<_> = album.tracks.iter().map(|x| x.title.as_str()).collect();
+    assert_eq!(titles, vec!["以父之名", "晴天"]);
+    assert_eq!(album.tracks[1].img_url, album.info.cover_img_url);
+
+    let artist = qq.get_artist("0025NhlN2yWrP4").await.unwrap();
+    assert_eq!(artist.info.name, "周杰伦");
+    assert_eq!(
+      artist.info.cover_img_url,
+      "https://y.gtimg.cn/music/photo_new/T001R300x300M0000025NhlN2yWrP4.jpg"
+    );
+    let ids: Vec<_> = artist.tracks.iter().map(|x| x.id.to_string()).collect();
+    assert_eq!(
+      ids,
+      vec!["qqtrack_0039MnYb0qxYhV", "qqtrack_004Z8Ihr0JIu5s"]
+    );
   }
 }
@@ -1,7 +1,16 @@
+use crate::media::ProviderError;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use md5::{Digest, Md5};
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::str::FromStr;
 use url::{ParseError, Url};
 use uuid::Uuid;
 
-const FAKE_WEBSITE: &'static str = "http://localhost:3030";
+const FAKE_WEBSITE: &str = "http://localhost:3030";
 
 pub fn create_url(relative_url: &str) -> Result<Url, ParseError> {
   let base = Url::parse(FAKE_WEBSITE).unwrap();
@@ -13,8 +22,238 @@ pub fn create_url(relative_url: &str) -> Result<Url, ParseError> {
 pub fn generate_uuid(use_separator: bool) -> String {
   let mut uid = Uuid::new_v4().to_string();
   if !use_separator {
-    uid = uid.replace("-", "")
+    uid = uid.replace('-', "")
   }
 
   uid
 }
+
+pub(crate) fn create_md5(data: impl AsRef<[u8]>) -> String {
+  let mut hasher = Md5::new();
+  hasher.update(data);
+  let hasher_output = hasher.finalize();
+
+  format!("{:x}", hasher_output)
+}
+
+pub(crate) fn endpoint_url(base: &str, path: &str) -> String {
+  format!("{}{}", base.trim_end_matches('/'), path)
+}
+
+/// Like `endpoint_url`, but a misconfigured base is reported instead of
+/// surfacing later as a panic.
+pub(crate) fn parse_endpoint(
+  provider: &'static str,
+  base: &str,
+  path: &str,
+) -> Result<Url, ProviderError> {
+  let url = endpoint_url(base, path);
+  Url::parse(&url).map_err(|_| ProviderError::invalid(provider, "endpoint", &url))
+}
+
+pub(crate) fn parse_endpoint_with_params<I, K, V>(
+  provider: &'static str,
+  base: &str,
+  path: &str,
+  params: I,
+) -> Result<Url, ProviderError>
+where
+  I: IntoIterator,
+  I::Item: Borrow<(K, V)>,
+  K: AsRef<str>,
+  V: AsRef<str>,
+{
+  let mut url = parse_endpoint(provider, base, path)?;
+  url.query_pairs_mut().extend_pairs(params);
+
+  Ok(url)
+}
+
+pub(crate) fn from_timestamp_millis(ms: i64) -> Option<DateTime<Utc>> {
+  if ms <= 0 {
+    return None;
+  }
+  Utc.timestamp_millis_opt(ms).single()
+}
+
+pub(crate) fn from_timestamp(secs: i64) -> Option<DateTime<Utc>> {
+  from_timestamp_millis(secs.checked_mul(1000)?)
+}
+
+pub(crate) fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+  let value = value.trim();
+  if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+    return Some(datetime.and_utc());
+  }
+  NaiveDate::parse_from_str(value, "%Y-%m-%d")
+    .ok()
+    .and_then(|date| date.and_hms_opt(0, 0, 0))
+    .map(|datetime| datetime.and_utc())
+}
+
+pub(crate) async fn send(
+  provider: &'static str,
+  request: RequestBuilder,
+) -> Result<(String, Response), ProviderError> {
+  let (client, request) = request.build_split();
+  let request = request.map_err(|source| ProviderError::Transport {
+    provider,
+    url: source.url().map(Url::to_string).unwrap_or_default(),
+    source,
+  })?;
+  let url = request.url().to_string();
+
+  let response = client
+    .execute(request)
+    .await
+    .map_err(|source| ProviderError::Transport {
+      provider,
+      url: url.clone(),
+      source,
+    })?;
+
+  let status = response.status();
+  if !status.is_success() {
+    return Err(ProviderError::Status {
+      provider,
+      url,
+      status,
+    });
+  }
+
+  Ok((url, response))
+}
+
+pub(crate) async fn fetch_text(
+  provider: &'static str,
+  request: RequestBuilder,
+) -> Result<(String, String), ProviderError> {
+  let (url, response) = send(provider, request).await?;
+  let text = response
+    .text()
+    .await
+    .map_err(|source| ProviderError::Transport {
+      provider,
+      url: url.clone(),
+      source,
+    })?;
+
+  Ok((url, text))
+}
+
+pub(crate) async fn fetch_json<T: DeserializeOwned>(
+  provider: &'static str,
+  request: RequestBuilder,
+) -> Result<(String, T), ProviderError> {
+  let (url, text) = fetch_text(provider, request).await?;
+  let data = decode_json(provider, &url, &text)?;
+
+  Ok((url, data))
+}
+
+pub(crate) fn decode_json<T: DeserializeOwned>(
+  provider: &'static str,
+  url: &str,
+  text: &str,
+) -> Result<T, ProviderError> {
+  serde_json::from_str(text).map_err(|e| ProviderError::Decode {
+    provider,
+    url: url.to_string(),
+    message: e.to_string(),
+  })
+}
+
+pub(crate) fn require_param<'a>(
+  provider: &'static str,
+  query: &'a HashMap<String, String>,
+  name: &str,
+) -> Result<&'a str, ProviderError> {
+  query
+    .get(name)
+    .map(String::as_str)
+    .ok_or_else(|| ProviderError::missing(provider, name))
+}
+
+pub(crate) fn parse_param<T: FromStr>(
+  provider: &'static str,
+  name: &str,
+  value: &str,
+) -> Result<T, ProviderError> {
+  value
+    .parse()
+    .map_err(|_| ProviderError::invalid(provider, name, value))
+}
+
+/// Decodes the HTML entities upstream APIs leave in titles and lyrics:
+/// the common named ones plus decimal and hex character references.
+pub(crate) fn html_decode(text: &str) -> String {
+  let mut result = String::with_capacity(text.len());
+  let mut rest = text;
+
+  while let Some(start) = rest.find('&') {
+    result.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    let decoded = rest[1..]
+      .find(';')
+      .filter(|end| *end <= 10)
+      .and_then(|end| Some((decode_entity(&rest[1..=end])?, end + 2)));
+    match decoded {
+      Some((c, len)) => {
+        result.push(c);
+        rest = &rest[len..];
+      }
+      None => {
+        result.push('&');
+        rest = &rest[1..];
+      }
+    }
+  }
+  result.push_str(rest);
+
+  result
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+  let code = match entity {
+    "amp" => return Some('&'),
+    "lt" => return Some('<'),
+    "gt" => return Some('>'),
+    "quot" => return Some('"'),
+    "apos" => return Some('\''),
+    "nbsp" => return Some('\u{a0}'),
+    _ => entity.strip_prefix('#')?,
+  };
+  let code = match code.strip_prefix(['x', 'X']) {
+    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+    None => code.parse().ok()?,
+  };
+
+  char::from_u32(code)
+}
+
+pub(crate) fn string_or_number<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> Result<Option<String>, D::Error> {
+  let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+
+  Ok(match value {
+    Some(serde_json::Value::String(s)) if !s.is_empty() => Some(s),
+    Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+    _ => None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::utils::html_decode;
+
+  #[test]
+  fn decode_html_entities() {
+    assert_eq!(
+      html_decode("Tom &amp; Jerry&#39;s &quot;Song&quot; &#x4E2D;&#25991; &lt;3"),
+      "Tom & Jerry's \"Song\" 中文 <3"
+    );
+    assert_eq!(html_decode("R&B &copy; &#xZZ; &"), "R&B &copy; &#xZZ; &");
+  }
+}